@@ -0,0 +1,38 @@
+//! Compares processing events against only exact assertions, which skips checking each
+//! assertion when an event can't match, with a linear scan over the same assertions.
+//!
+//! Run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+use tracing_subscriber::layer::SubscriberExt;
+
+const ASSERTIONS: usize = 1_000;
+const EVENTS: u32 = 10_000;
+
+fn bench(name: &str, register: impl Fn(&tracing_assertions::Layer)) -> Duration {
+    let asserter = tracing_assertions::Layer::default();
+    let subscriber = tracing_subscriber::Registry::default().with(asserter.clone());
+    let _guard = tracing::subscriber::set_default(subscriber);
+    let assertions = (0..ASSERTIONS)
+        .map(|i| asserter.matches(format!("pattern {i}")))
+        .collect::<Vec<_>>();
+    register(&asserter);
+    let start = Instant::now();
+    for _ in 0..EVENTS {
+        tracing::info!("unmatched");
+    }
+    let elapsed = start.elapsed();
+    println!("{name}: {:?} per event", elapsed / EVENTS);
+    drop(assertions);
+    elapsed
+}
+
+fn main() {
+    let fast = bench("exact", |_| {});
+    // A single non-exact assertion requires checking every assertion. Assertions stay
+    // registered when dropped.
+    let scan = bench("linear scan", |asserter| {
+        let _ = asserter.matches_unicode_ci("other");
+    });
+    println!("speedup: {:.1}x", scan.as_secs_f64() / fast.as_secs_f64());
+}
@@ -28,7 +28,8 @@
 //! ### Failing
 //!
 //! When failing e.g.
-//! ```should_panic
+#![cfg_attr(not(feature = "debug-only"), doc = "```should_panic")]
+#![cfg_attr(feature = "debug-only", doc = "```ignore")]
 //! # use tracing_subscriber::layer::SubscriberExt;
 //! # let asserter = tracing_assertions::Layer::default();
 //! # let registry = tracing_subscriber::Registry::default();
@@ -69,23 +70,75 @@
 //! ```
 //! Calling [`Assertion::reset`] on `one` does not affect the value of `and` and calling [`Assertion::reset`] on `and` does not affect the value of `two`.
 //!
+//! ### `debug-only`
+//!
+//! With the `debug-only` feature assertions are only checked in builds with
+//! `debug_assertions`, like [`debug_assert!`]. In other builds (e.g. `--release`)
+//! asserting, e.g. with [`Assertion::assert`] or [`Assertion::try_assert`], does
+//! nothing. Events are still matched, so [`bool::from`] and the other queries of an
+//! assertion do not depend on the build profile.
+//!
+//! ### `creation-location`
+//!
+//! With the `creation-location` feature each assertion records where it was created,
+//! which is included when it fails, e.g. `"one" (created at src/lib.rs:12:15)`. This
+//! tells apart assertions created by a shared helper.
+//!
+//! ### `async`
+//!
+//! With the `async` feature [`Assertion::wait`] and [`Assertion::wait_yielding`] wait
+//! for an assertion without blocking the thread, so events logged by other tasks on the
+//! same thread are processed meanwhile. They are woken by the layer rather than by a
+//! runtime primitive like `tokio::sync::Notify`, so work with any runtime. The `tokio`
+//! feature is an alias of `async`.
+//!
+//! ### `log`
+//!
+//! Records from crates using [log](https://docs.rs/log/latest/log/) can be asserted on
+//! when bridged to tracing with [tracing-log](https://docs.rs/tracing-log/latest/tracing_log/).
+//! ```
+//! # use tracing_subscriber::layer::SubscriberExt;
+//! # let asserter = tracing_assertions::Layer::default();
+//! # let registry = tracing_subscriber::Registry::default();
+//! # let subscriber = registry.with(asserter.clone());
+//! # let guard = tracing::subscriber::set_default(subscriber);
+//! tracing_log::LogTracer::init().unwrap();
+//! let one = asserter.matches("one");
+//! log::info!("one");
+//! one.assert();
+//! ```
+//!
 //! ### Similar crates
 //! - [test-log](https://crates.io/crates/test-log): A replacement of the `#[test]` attribute that initializes logging and/or tracing infrastructure before running tests.
 //! - [tracing_test](https://crates.io/crates/tracing-test): Helper functions and macros that allow for easier testing of crates that use `tracing`.
 //! - [tracing-fluent-assertions](https://crates.io/crates/tracing-fluent-assertions): An fluent assertions framework for tracing.
 //!
 
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt::Debug;
-use std::ops::{BitAnd, BitOr};
-use std::sync::atomic::AtomicBool;
+use std::io::IsTerminal;
+use std::ops::{BitAnd, BitOr, BitXor};
+#[cfg(feature = "creation-location")]
+use std::panic::Location;
+use std::panic::RefUnwindSafe;
 use std::sync::atomic::Ordering::SeqCst;
-use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
+use std::sync::{Arc, Weak};
+use std::sync::{Condvar, Mutex, OnceLock};
+#[cfg(feature = "async")]
+use std::task::{Poll, Waker};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
 use tracing::field::Field;
+use tracing::span::{Attributes, Id, Record};
 use tracing::Event;
+use tracing::Level;
+use tracing::Metadata;
 use tracing::Subscriber;
 use tracing_subscriber::field::Visit;
 use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
 
 #[cfg(feature = "regex")]
 use regex::Regex;
@@ -94,34 +147,494 @@ use regex::Regex;
 #[derive(Default, Clone, Debug)]
 pub struct Layer(Arc<InnerLayer>);
 
+/// Whether assertions are checked, see the `debug-only` feature.
+const CHECKED: bool = cfg!(any(debug_assertions, not(feature = "debug-only")));
+
 /// The inner layer shared between assertions and the assertion layer.
 ///
 /// You should probably not use this directly.
 #[derive(Default, Debug)]
 struct InnerLayer {
     pass_all: AtomicBool,
-    assertions: Mutex<Vec<Arc<InnerAssertion>>>,
+    assertions: Mutex<Registered>,
+    sequences: Mutex<Vec<Arc<InnerSequence>>>,
+    /// Notified, with `assertions`, whenever an event has been processed.
+    processed: Condvar,
+    /// The events being processed, see [`Layer::barrier`].
+    in_flight: Mutex<InFlight>,
+    /// Notified, with `in_flight`, whenever an event has finished being processed.
+    landed: Condvar,
+    /// Woken whenever an event has been processed, see [`Assertion::wait_yielding`].
+    #[cfg(feature = "async")]
+    wakers: Mutex<Vec<Waker>>,
+    on_match: Mutex<MatchCallbacks>,
+    on_pattern: Mutex<PatternCallbacks>,
+    /// The maximum number of registered assertions, see [`LayerBuilder::bounded`].
+    capacity: Option<usize>,
+    scope: MatchScope,
+    /// The first event processed, see [`Layer::first_event`].
+    first_event: OnceLock<FirstEvent>,
+    /// The number of events processed, see [`Layer::checkpoint`].
+    events: AtomicU64,
+    /// The layer which absorbed this layer, see [`Layer::absorb`].
+    absorbed_into: Mutex<Option<Arc<InnerLayer>>>,
+    /// Messages which reset the matched assertions, see [`Layer::reset_on`].
+    reset_markers: Mutex<Vec<String>>,
+    /// The assertions matched since the last reset marker, when there are reset markers.
+    matched: Mutex<Vec<Weak<InnerAssertion>>>,
+    /// All the assertions matched since the last [`Layer::reset_all`].
+    consumed: Mutex<Vec<Weak<InnerAssertion>>>,
+    /// The assertions which failed and were unregistered, see [`Layer::expect_next`].
+    failed: Mutex<Vec<Weak<InnerAssertion>>>,
+    /// The combined assertions with leaves created by this layer, see [`Layer::failing`].
+    roots: Mutex<Vec<Weak<Shape>>>,
+    /// Whether to record messages, see [`LayerBuilder::recording`].
+    recording: bool,
+    /// The messages of the events processed by level, when recording.
+    recorded: Mutex<HashMap<Level, Vec<String>>>,
+    /// The events processed in order, when recording.
+    transcript: Mutex<Vec<RecordedEvent>>,
+    /// The targets of the events processed, when recording.
+    targets: Mutex<HashSet<String>>,
+    /// The types each event field was recorded as, when recording.
+    field_types: Mutex<HashMap<&'static str, HashSet<FieldType>>>,
+    /// Whether failed assertions are recorded rather than panicking, see [`Layer::soft_mode`].
+    soft: AtomicBool,
+    /// The failures recorded in soft mode, see [`Layer::flush_failures`].
+    failures: Mutex<Vec<String>>,
+    color: Color,
+}
+
+/// Whether failures are shown in colour, see [`Layer::set_color`].
+#[derive(Debug)]
+struct Color(AtomicBool);
+
+impl Default for Color {
+    fn default() -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self(AtomicBool::new(
+            !no_color && std::io::stdout().is_terminal(),
+        ))
+    }
+}
+
+/// An event recorded in the transcript of a layer, see [`Layer::snapshot`].
+#[derive(Debug)]
+struct RecordedEvent {
+    level: Level,
+    target: &'static str,
+    message: String,
+}
+
+/// The parts of the first event processed which are recorded.
+#[derive(Debug)]
+struct FirstEvent {
+    message: String,
+    level: Level,
+}
+
+/// The assertions registered with a layer, waiting for a match.
+#[derive(Debug, Default)]
+struct Registered {
+    assertions: VecDeque<Arc<InnerAssertion>>,
+    /// The number of registered exact assertions by pattern, see [`AssertionSpec::exact_pattern`].
+    ///
+    /// Static patterns are borrowed, so [`Layer::matches_static`] does not copy them.
+    exact: HashMap<Cow<'static, str>, usize>,
+    /// The number of registered assertions which are not exact.
+    other: usize,
+}
+
+impl Registered {
+    /// Registers an assertion, evicting the oldest assertions beyond `capacity`.
+    fn push(&mut self, assertion: Arc<InnerAssertion>, capacity: Option<usize>) {
+        match assertion.spec.exact_key() {
+            Some(pattern) => *self.exact.entry(pattern).or_default() += 1,
+            None => self.other += 1,
+        }
+        self.assertions.push_back(assertion);
+        if let Some(capacity) = capacity {
+            while self.assertions.len() > capacity {
+                self.remove(0);
+            }
+        }
+    }
+    /// Unregisters the assertion at `index`.
+    fn remove(&mut self, index: usize) {
+        let Some(assertion) = self.assertions.remove(index) else {
+            return;
+        };
+        match assertion.spec.exact_pattern() {
+            Some(pattern) => {
+                let count = self.exact.get_mut(pattern).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    self.exact.remove(pattern);
+                }
+            }
+            None => self.other -= 1,
+        }
+    }
+    /// Unregisters all the assertions, returning them.
+    fn take(&mut self) -> VecDeque<Arc<InnerAssertion>> {
+        std::mem::take(self).assertions
+    }
+    /// Returns whether a message could match any of the registered assertions.
+    ///
+    /// When only exact assertions are registered this is a single lookup, so most
+    /// events can skip checking each assertion.
+    fn may_match(&self, message: &str) -> bool {
+        self.other > 0 || self.exact.contains_key(message)
+    }
+}
+
+impl std::ops::Deref for Registered {
+    type Target = VecDeque<Arc<InnerAssertion>>;
+    fn deref(&self) -> &Self::Target {
+        &self.assertions
+    }
+}
+
+/// A callback invoked when an assertion matches, see [`Layer::on_match`].
+type MatchCallback = Arc<dyn Fn(&str, u64) + Send + Sync>;
+
+/// The callbacks invoked when an assertion matches.
+#[derive(Default, Clone)]
+struct MatchCallbacks(Vec<MatchCallback>);
+
+impl Debug for MatchCallbacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MatchCallbacks({})", self.0.len())
+    }
+}
+
+/// A callback invoked when a message matches a pattern, see [`Layer::on_pattern`].
+type PatternCallback = Arc<Mutex<dyn FnMut(&str) + Send>>;
+
+/// The callbacks invoked when a message matches their pattern.
+#[derive(Default, Clone)]
+struct PatternCallbacks(Vec<(String, PatternCallback)>);
+
+impl Debug for PatternCallbacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PatternCallbacks({})", self.0.len())
+    }
+}
+
+/// What part of an event is matched as its message, see [`LayerBuilder::match_scope`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MatchScope {
+    /// The `message` field of the event, or when there isn't one the last recorded field
+    /// which is not a `log.` field added by `tracing-log`.
+    #[default]
+    Message,
+    /// The `Debug` renderings of all fields of the event, in declaration order,
+    /// joined by single spaces.
+    ///
+    /// For `info!(user = "bob", count = 3, "login")` this is `login "bob" 3` (the
+    /// message is always declared first).
+    AllFields,
+}
+
+/// A builder for a [`Layer`].
+///
+/// ```
+/// let asserter = tracing_assertions::Layer::builder().bounded(1024).build();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct LayerBuilder {
+    capacity: Option<usize>,
+    scope: MatchScope,
+    recording: bool,
+    /// `None` to detect whether to use colour, see [`Layer::set_color`].
+    color: Option<bool>,
+    disabled: bool,
+}
+
+impl LayerBuilder {
+    /// Bounds the number of registered assertions to `capacity`.
+    ///
+    /// By default the registry of assertions waiting for a match is unbounded,
+    /// which is a problem for long running tests that create many transient
+    /// assertions.
+    ///
+    /// When registering an assertion would exceed the capacity, the oldest
+    /// registered assertion is evicted. An evicted assertion no longer observes
+    /// events, so it keeps whatever value it had (false, unless it was matched
+    /// and then [`Assertion::reset`]). Resetting or repeating an assertion
+    /// registers it again as the newest.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::builder().bounded(1).build();
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// asserter.check_message("one");
+    /// asserter.check_message("two");
+    /// (!&one).assert();
+    /// two.assert();
+    /// ```
+    #[must_use]
+    pub fn bounded(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+    /// Sets what part of an event is matched as its message.
+    ///
+    /// ```
+    /// use tracing_assertions::MatchScope;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::builder()
+    ///     .match_scope(MatchScope::AllFields)
+    ///     .build();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let login = asserter.matches("login \"bob\"");
+    /// tracing::info!(user = "bob", "login");
+    /// login.assert();
+    /// ```
+    #[must_use]
+    pub fn match_scope(mut self, scope: MatchScope) -> Self {
+        self.scope = scope;
+        self
+    }
+    /// Records the message, level, target and field types of every event, see
+    /// [`Layer::recorded_messages`], [`Layer::recorded_at_level`], [`Layer::snapshot`],
+    /// [`Layer::level_sequence`], [`Layer::level_ratio`], [`Layer::targets_seen`] and
+    /// [`Layer::assert_field_type`].
+    ///
+    /// Recording is off by default as the messages are kept for the life of the layer.
+    #[must_use]
+    pub fn recording(mut self) -> Self {
+        self.recording = true;
+        self
+    }
+    /// Sets whether failures are shown in colour, see [`Layer::set_color`].
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::builder().color(false).build();
+    /// ```
+    #[must_use]
+    pub fn color(mut self, enabled: bool) -> Self {
+        self.color = Some(enabled);
+        self
+    }
+    /// Builds the layer [disabled](Layer::disable), so all assertions pass until
+    /// [`Layer::enable`] is called.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::builder().disabled().build();
+    /// asserter.matches("missing").assert();
+    /// ```
+    #[must_use]
+    pub fn disabled(mut self) -> Self {
+        self.disabled = true;
+        self
+    }
+    /// Builds the layer.
+    #[must_use]
+    pub fn build(self) -> Layer {
+        Layer(Arc::new(InnerLayer {
+            capacity: self.capacity,
+            scope: self.scope,
+            recording: self.recording,
+            color: self
+                .color
+                .map_or_else(Color::default, |color| Color(AtomicBool::new(color))),
+            pass_all: AtomicBool::new(self.disabled),
+            ..InnerLayer::default()
+        }))
+    }
 }
 
 impl Layer {
+    /// Creates a builder to configure a layer.
+    ///
+    /// [`Layer::default`] is equivalent to `Layer::builder().build()`.
+    #[must_use]
+    pub fn builder() -> LayerBuilder {
+        LayerBuilder::default()
+    }
+    /// Creates a layer and sets it as the default subscriber for the current thread, see
+    /// [`Layer::set_default`].
+    ///
+    /// Dropping the returned guard restores the previous default subscriber.
+    ///
+    /// ```
+    /// let (asserter, guard) = tracing_assertions::Layer::install();
+    /// let one = asserter.matches("one");
+    /// tracing::info!("one");
+    /// drop(guard);
+    /// one.assert();
+    /// ```
+    #[must_use = "the subscriber is unset when the guard is dropped"]
+    pub fn install() -> (Layer, LayerGuard) {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        (asserter, guard)
+    }
+    /// Sets a [`tracing_subscriber::Registry`] with this layer as the default subscriber
+    /// for the current thread.
+    ///
+    /// The default is only set for the current thread, so events from other threads only
+    /// reach this layer through subscribers of their own holding it, e.g. a
+    /// [`tracing::Dispatch`] shared with them. When the returned guard is dropped it calls
+    /// [`Layer::barrier`] before restoring the previous default subscriber, so events which
+    /// other threads had started processing by then, callbacks included, are fully
+    /// processed once the drop returns. Events started after the drop may or may not have
+    /// been processed.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let one = asserter.matches("one");
+    /// tracing::info!("one");
+    /// drop(guard);
+    /// one.assert();
+    /// ```
+    #[must_use = "the subscriber is unset when the guard is dropped"]
+    pub fn set_default(&self) -> LayerGuard {
+        use tracing_subscriber::layer::SubscriberExt;
+        let subscriber = tracing_subscriber::Registry::default().with(self.clone());
+        LayerGuard {
+            asserter: self.clone(),
+            _guard: tracing::subscriber::set_default(subscriber),
+        }
+    }
     /// Creates a string matching assertion.
     ///
     /// # Panics
     ///
     /// When the internal mutex is poisoned.
+    #[track_caller]
     pub fn matches(&self, s: impl Into<String>) -> Assertion {
-        let inner_assertion = Arc::new(InnerAssertion {
-            boolean: AtomicBool::new(false),
-            assertion_type: AssertionType::Matches(s.into()),
-        });
-        self.0
-            .assertions
-            .lock()
-            .unwrap()
-            .push(inner_assertion.clone());
-        Assertion(AssertionWrapper::One {
-            assertion: inner_assertion.clone(),
-            asserter: self.0.clone(),
+        self.register(AssertionType::Matches(s.into()))
+    }
+    /// Creates a string matching assertion from a static string, without copying `s`.
+    ///
+    /// Behaves as [`Layer::matches`], avoiding the copy of `s` when creating many
+    /// assertions, e.g. in a benchmark loop. The assertion itself is still allocated.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches_static("one");
+    /// asserter.check_message("one");
+    /// one.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    #[track_caller]
+    pub fn matches_static(&self, s: &'static str) -> Assertion {
+        self.register(AssertionType::MatchesStatic(s))
+    }
+    /// Creates a string matching assertion which is not consumed when it matches.
+    ///
+    /// Equivalent to `asserter.matches(s).persist()`, see [`Assertion::persist`].
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let heartbeat = asserter.matches_persistent("heartbeat");
+    /// tracing::info!("heartbeat");
+    /// let first = heartbeat.matched_at();
+    /// tracing::info!("heartbeat");
+    /// assert!(heartbeat.assert().matched_at() > first);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    #[track_caller]
+    pub fn matches_persistent(&self, s: impl Into<String>) -> Assertion {
+        self.matches(s).persist()
+    }
+    /// Creates an assertion that a message containing `s` was logged.
+    ///
+    /// Unlike [`Layer::matches`] the message may have other text around `s`, such as ids
+    /// or timestamps.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let connected = asserter.contains("connected to");
+    /// tracing::info!("[12:00:01] connected to 10.0.0.1");
+    /// connected.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn contains(&self, s: impl Into<String>) -> Assertion {
+        self.register(AssertionType::Contains(s.into()))
+    }
+    /// Creates an assertion that a message starting with `s` was logged.
+    ///
+    /// Failures show the pattern as `s*`.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let request = asserter.starts_with("[req 42]") & asserter.ends_with("completed");
+    /// tracing::info!("[req 42] started");
+    /// (!&request).assert();
+    /// tracing::info!("[req 7] completed");
+    /// request.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn starts_with(&self, s: impl Into<String>) -> Assertion {
+        self.register(AssertionType::StartsWith(s.into()))
+    }
+    /// Creates an assertion that a message ending with `s` was logged.
+    ///
+    /// Failures show the pattern as `*s`, see [`Layer::starts_with`].
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn ends_with(&self, s: impl Into<String>) -> Assertion {
+        self.register(AssertionType::EndsWith(s.into()))
+    }
+    /// Creates an assertion that a message equal to `s` is never logged.
+    ///
+    /// The assertion starts true and becomes false once a matching event is logged. It
+    /// is `!` of a [persistent](Assertion::persist) [`Layer::matches`], so it keeps
+    /// watching after the first match. [`Assertion::reset`] makes it true again until the
+    /// next match, and [`Assertion::repeat`] creates a copy which is true.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let no_panic = asserter.never("panicked");
+    /// tracing::info!("started");
+    /// no_panic.assert();
+    /// tracing::error!("panicked");
+    /// (!&no_panic).assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn never(&self, s: impl Into<String>) -> Assertion {
+        // Built directly as `!` of an owned assertion registers a copy.
+        Assertion::rooted(AssertionWrapper::Not {
+            assertion: Box::new(self.matches(s).persist()),
         })
     }
     /// Creates a string matching assertion on the debug string of a value.
@@ -147,142 +660,238 @@ impl Layer {
     /// # let subscriber = base_subscriber.with(asserter.clone());
     /// # let guard = tracing::subscriber::set_default(subscriber);
     /// let condition = asserter.matches(format!("{:?}", MyStruct { x: 2, y: 3 }));
+    #[track_caller]
     pub fn debug(&self, s: impl Debug) -> Assertion {
         self.matches(format!("{s:?}"))
     }
-    /// Creates a regex matching assertion.
+    /// Creates a string matching assertion which only matches the `n`th matching message,
+    /// counting from `1`.
     ///
-    /// # Errors
+    /// Until `n` matching messages have been logged the assertion is false. Resetting the
+    /// assertion restarts the count.
     ///
-    /// When the conversion to [`Regex`] fails.
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let third = asserter.matches_nth("retrying", 3);
+    /// asserter.check_message("retrying");
+    /// asserter.check_message("retrying");
+    /// third.assert_false();
+    /// asserter.check_message("retrying");
+    /// third.assert();
+    /// ```
     ///
     /// # Panics
     ///
-    /// When the internal mutex is poisoned.
-    #[cfg(feature = "regex")]
-    pub fn regex<T>(&self, s: T) -> Result<Assertion, <Regex as TryFrom<T>>::Error>
-    where
-        Regex: TryFrom<T>,
-    {
-        let inner_assertion = Arc::new(InnerAssertion {
-            boolean: AtomicBool::new(false),
-            assertion_type: AssertionType::Regex(Regex::try_from(s)?),
-        });
-        self.0
-            .assertions
-            .lock()
-            .unwrap()
-            .push(inner_assertion.clone());
-        Ok(Assertion(AssertionWrapper::One {
-            assertion: inner_assertion.clone(),
-            asserter: self.0.clone(),
-        }))
-    }
-    /// The inverse of [`Layer::disable`].
-    pub fn enable(&self) {
-        self.0.pass_all.store(false, SeqCst);
+    /// When `n` is `0` or the internal mutex is poisoned.
+    #[track_caller]
+    pub fn matches_nth(&self, s: impl Into<String>, n: usize) -> Assertion {
+        assert_ne!(n, 0, "occurrences are counted from 1");
+        self.register_spec(AssertionSpec {
+            nth: Some(n),
+            ..AssertionSpec::new(AssertionType::Matches(s.into()))
+        })
     }
-    /// Tells all assertions to pass.
+    /// Creates a string matching assertion which is true once `s` has been logged at least
+    /// `n` times.
     ///
-    /// Useful when you want to disables certain tested logs in a
-    /// test for debugging without needing to comment out all the
-    /// assertions you added.
-    pub fn disable(&self) {
-        self.0.pass_all.store(true, SeqCst);
+    /// Unlike [`Layer::count`] this is a regular assertion so composes with `&` and `|`.
+    /// It is equivalent to [`Layer::matches_nth`]. [Resetting](Assertion::reset) it sets
+    /// the count back to zero, even before it reached `n`.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let retried = asserter.matches_times("retrying", 3) & asserter.matches("connected");
+    /// asserter.check_message("retrying");
+    /// asserter.check_message("retrying");
+    /// asserter.check_message("connected");
+    /// (!&retried).assert();
+    /// asserter.check_message("retrying");
+    /// retried.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When `n` is `0` or the internal mutex is poisoned.
+    #[track_caller]
+    pub fn matches_times(&self, s: impl Into<String>, n: usize) -> Assertion {
+        self.matches_nth(s, n)
     }
-}
-
-#[derive(Debug, Clone)]
-enum AssertionType {
-    Matches(String),
-    #[cfg(feature = "regex")]
-    Regex(Regex),
-}
-
-impl std::fmt::Display for AssertionType {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        use AssertionType::*;
-        match self {
-            Matches(matches) => write!(f, "{matches}"),
-            #[cfg(feature = "regex")]
-            Regex(regex) => write!(f, "{regex}"),
+    /// Creates a string matching assertion which only considers events logged at `min` or
+    /// less verbose levels, e.g. `WARN` and `ERROR` for a minimum of `WARN`.
+    ///
+    /// Messages passed to [`Layer::check_message`] have no level, so never match.
+    ///
+    /// ```
+    /// use tracing::Level;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let failed = asserter.matches_at_level("disk full", Level::WARN);
+    /// tracing::debug!("disk full");
+    /// failed.assert_false();
+    /// tracing::error!("disk full");
+    /// failed.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    #[track_caller]
+    pub fn matches_at_level(&self, s: impl Into<String>, min: Level) -> Assertion {
+        self.register_spec(AssertionSpec {
+            min_level: Some(min),
+            ..AssertionSpec::new(AssertionType::Matches(s.into()))
+        })
+    }
+    /// Creates a string matching assertion which only considers events emitted on the
+    /// thread named `thread_name`.
+    ///
+    /// Tracing does not record threads, so this reads the name of the thread processing
+    /// the event, which is the thread that emitted it. Unnamed threads never match.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let done = asserter.matches_from_thread("done", "worker");
+    /// asserter.check_message("done");
+    /// done.assert_false();
+    /// let worker = asserter.clone();
+    /// std::thread::Builder::new()
+    ///     .name("worker".into())
+    ///     .spawn(move || worker.check_message("done"))
+    ///     .unwrap()
+    ///     .join()
+    ///     .unwrap();
+    /// done.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    #[track_caller]
+    pub fn matches_from_thread(&self, s: impl Into<String>, thread_name: &str) -> Assertion {
+        self.register_spec(AssertionSpec {
+            thread: Some(thread_name.to_string()),
+            ..AssertionSpec::new(AssertionType::Matches(s.into()))
+        })
+    }
+    /// Creates an assertion that exactly one event with the message `s` was logged at `level`.
+    ///
+    /// Events with the message at other levels are allowed. The assertion keeps counting
+    /// after it is first satisfied, so a second event at `level` makes it false.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let started = asserter.exactly_once_at_level(tracing::Level::INFO, "started");
+    /// tracing::debug!("started");
+    /// tracing::info!("started");
+    /// started.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn exactly_once_at_level(
+        &self,
+        level: Level,
+        s: impl Into<String>,
+    ) -> ExactlyOnceAssertion {
+        let spec = AssertionSpec::exact(s);
+        let any = Arc::new(InnerAssertion {
+            persistent: AtomicBool::new(true),
+            ..InnerAssertion::new(spec.clone())
+        });
+        let at_level = Arc::new(InnerAssertion {
+            persistent: AtomicBool::new(true),
+            ..InnerAssertion::new(spec.with_level(level))
+        });
+        self.0.register(any.clone());
+        self.0.register(at_level.clone());
+        ExactlyOnceAssertion {
+            any,
+            at_level,
+            level,
+            asserter: self.0.clone(),
         }
     }
-}
-
-/// An assertion.
-#[derive(Debug, Clone)]
-pub struct Assertion(AssertionWrapper);
-
-/// This exists since there is no way of making enum variants private.
-#[derive(Debug)]
-enum AssertionWrapper {
-    And {
-        lhs: Box<Assertion>,
-        rhs: Box<Assertion>,
-    },
-    Or {
-        lhs: Box<Assertion>,
-        rhs: Box<Assertion>,
-    },
-    One {
-        assertion: Arc<InnerAssertion>,
-        asserter: Arc<InnerLayer>,
-    },
-    Not {
-        assertion: Box<Assertion>,
-    },
-}
-impl Clone for AssertionWrapper {
-    fn clone(&self) -> AssertionWrapper {
-        use AssertionWrapper::*;
-        match &self {
-            One {
-                assertion,
-                asserter,
-            } => {
-                let new_assertion = Arc::new(InnerAssertion {
-                    boolean: AtomicBool::from(assertion.boolean.load(SeqCst)),
-                    assertion_type: assertion.assertion_type.clone(),
-                });
-                asserter
-                    .assertions
-                    .lock()
-                    .unwrap()
-                    .push(new_assertion.clone());
-                One {
-                    assertion: new_assertion,
-                    asserter: asserter.clone(),
-                }
-            }
-            Not { assertion } => Not {
-                assertion: assertion.clone(),
-            },
-            And { lhs, rhs } => And {
-                lhs: lhs.clone(),
-                rhs: rhs.clone(),
-            },
-            Or { lhs, rhs } => Or {
-                lhs: lhs.clone(),
-                rhs: rhs.clone(),
-            },
+    /// Creates an assertion on the number of events with the message `s` which are logged.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let requests = asserter.count("request handled");
+    /// for _ in 0..3 {
+    ///     tracing::info!("request handled");
+    /// }
+    /// requests.assert_eq(3).assert_at_least(1).assert_at_most(5);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn count(&self, s: impl Into<String>) -> CountAssertion {
+        let assertion = Arc::new(InnerAssertion {
+            persistent: AtomicBool::new(true),
+            ..InnerAssertion::new(AssertionSpec::exact(s))
+        });
+        self.0.register(assertion.clone());
+        CountAssertion {
+            assertion,
+            asserter: self.0.clone(),
         }
     }
-}
-
-impl Assertion {
-    /// Evaluates the assertion.
+    /// Creates an assertion on the number of events logged directly within a span named
+    /// `span_name`.
+    ///
+    /// Only events whose innermost span is `span_name` are counted, not those within its
+    /// child spans. This requires the subscriber to track spans, e.g.
+    /// [`tracing_subscriber::Registry`].
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let events = asserter.events_in_span("process_batch");
+    /// tracing::info_span!("process_batch").in_scope(|| {
+    ///     tracing::info!("start");
+    ///     tracing::info!("end");
+    /// });
+    /// tracing::info!("outside");
+    /// events.assert_eq(2);
+    /// ```
     ///
     /// # Panics
     ///
-    /// When the assertion is false.
-    #[allow(clippy::must_use_candidate)] // `let _ = x.assert();` is ugly.
+    /// When the internal mutex is poisoned.
+    #[must_use]
     #[track_caller]
-    pub fn assert(&self) -> &Self {
-        assert!(bool::from(self), "{}", self.ansi());
-        self
+    pub fn events_in_span(&self, span_name: &str) -> CountAssertion {
+        let assertion = Arc::new(InnerAssertion {
+            persistent: AtomicBool::new(true),
+            ..InnerAssertion::new(AssertionSpec::new(AssertionType::InSpan(
+                span_name.to_string(),
+            )))
+        });
+        self.0.register(assertion.clone());
+        CountAssertion {
+            assertion,
+            asserter: self.0.clone(),
+        }
     }
-    /// Create a new assertion with the same condition.
+    /// Creates a string matching assertion which only considers the next event processed.
+    ///
+    /// If the next event does not match the assertion fails and no later event can
+    /// satisfy it, which [`Assertion::status`] reports as [`AssertionStatus::Failed`].
+    /// Until another event is processed the assertion is pending. Resetting a failed
+    /// assertion makes it consider the next event again.
     ///
     /// ```
     /// use tracing_subscriber::layer::SubscriberExt;
@@ -290,56 +899,32 @@ impl Assertion {
     /// let registry = tracing_subscriber::Registry::default();
     /// let subscriber = registry.with(asserter.clone());
     /// let guard = tracing::subscriber::set_default(subscriber);
-    /// let one = asserter.matches("one");
-    /// tracing::info!("one");
-    /// one.assert();
-    /// let one2 = one.repeat();
-    /// (!&one2).assert();
-    /// tracing::info!("one");
-    /// one2.assert();
+    /// let syn = asserter.expect_next("syn");
+    /// tracing::info!("syn");
+    /// syn.assert();
+    /// let ack = asserter.expect_next("ack");
+    /// tracing::info!("rst");
+    /// tracing::info!("ack");
+    /// (!&ack).assert();
     /// ```
     ///
     /// # Panics
     ///
-    /// When the inner mutex is poisoned.
-    #[must_use]
-    pub fn repeat(&self) -> Self {
-        use AssertionWrapper::*;
-        let inner = match &self.0 {
-            One {
-                assertion,
-                asserter,
-            } => {
-                let new_assertion = Arc::new(InnerAssertion {
-                    boolean: AtomicBool::new(false),
-                    assertion_type: assertion.assertion_type.clone(),
-                });
-                asserter
-                    .assertions
-                    .lock()
-                    .unwrap()
-                    .push(new_assertion.clone());
-                One {
-                    assertion: new_assertion,
-                    asserter: asserter.clone(),
-                }
-            }
-            Not { assertion } => Not {
-                assertion: Box::new(assertion.repeat()),
-            },
-            And { lhs, rhs } => And {
-                lhs: Box::new(lhs.repeat()),
-                rhs: Box::new(rhs.repeat()),
-            },
-            Or { lhs, rhs } => Or {
-                lhs: Box::new(lhs.repeat()),
-                rhs: Box::new(rhs.repeat()),
-            },
-        };
-        Self(inner)
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn expect_next(&self, s: impl Into<String>) -> Assertion {
+        self.register_spec(AssertionSpec {
+            next_only: true,
+            ..AssertionSpec::new(AssertionType::Matches(s.into()))
+        })
     }
-
-    /// Resets the assertion.
+    /// Creates a string matching assertion which only considers events with the given name.
+    ///
+    /// This compares against [`Metadata::name`]. Unless an event is given an explicit
+    /// name (e.g. `tracing::event!(name: "startup", Level::INFO, "ready")`) tracing
+    /// names it after its source location, e.g. `"event src/main.rs:42"`.
+    ///
+    /// Messages passed to [`Layer::check_message`] have no name, so never match.
     ///
     /// ```
     /// use tracing_subscriber::layer::SubscriberExt;
@@ -347,483 +932,8715 @@ impl Assertion {
     /// let registry = tracing_subscriber::Registry::default();
     /// let subscriber = registry.with(asserter.clone());
     /// let guard = tracing::subscriber::set_default(subscriber);
-    /// let one = asserter.matches("one");
-    /// tracing::info!("one");
-    /// one.assert().reset();
-    /// (!&one).assert();
-    /// tracing::info!("one");
-    /// one.assert();
+    /// let ready = asserter.with_event_name("startup", "ready");
+    /// tracing::info!("ready");
+    /// (!&ready).assert();
+    /// tracing::event!(name: "startup", tracing::Level::INFO, "ready");
+    /// ready.assert();
     /// ```
     ///
     /// # Panics
     ///
-    /// When the inner mutex is poisoned.
-    pub fn reset(&self) {
-        use AssertionWrapper::*;
-        match &self.0 {
-            One {
-                assertion,
-                asserter,
-            } => {
-                if assertion.boolean.swap(false, SeqCst) {
-                    asserter.assertions.lock().unwrap().push(assertion.clone());
-                }
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn with_event_name(&self, name: impl Into<String>, s: impl Into<String>) -> Assertion {
+        self.register_spec(AssertionSpec {
+            event_name: Some(name.into()),
+            ..AssertionSpec::new(AssertionType::Matches(s.into()))
+        })
+    }
+    /// Creates an assertion that an event had a field whose `Display` rendering equals `value`.
+    ///
+    /// Fields recorded as strings compare unquoted e.g. `info!(user = "bob")`
+    /// matches `field_display_eq("user", "bob")`, as do fields recorded with the
+    /// `%` sigil.
+    ///
+    /// Tracing passes both `%` and `?` values to the same recorder so these can't
+    /// be told apart, a field recorded with `?` matches when its `Debug` rendering
+    /// equals `value`.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let user = asserter.field_display_eq("user", "bob");
+    /// let count = asserter.field_display_eq("count", "3");
+    /// tracing::info!(user = "bob", count = 3, "login");
+    /// user.assert();
+    /// count.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn field_display_eq(&self, name: impl Into<String>, value: impl Into<String>) -> Assertion {
+        self.register(AssertionType::FieldDisplay {
+            name: name.into(),
+            value: value.into(),
+        })
+    }
+    /// Creates an assertion that an event had a field `name` recorded as `value`.
+    ///
+    /// Shorthand for [`Layer::field_display_eq`], strings compare unquoted and fields
+    /// recorded with `?` compare by their `Debug` rendering.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let status = asserter.field("status", "404");
+    /// let response = asserter.matches("response");
+    /// tracing::info!(status = 404, "response");
+    /// status.assert();
+    /// response.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn field(&self, name: &str, value: impl Into<String>) -> Assertion {
+        self.field_display_eq(name, value)
+    }
+    /// Creates an assertion that an event had a field whose `Debug` rendering equals `value`.
+    ///
+    /// Fields recorded as strings compare quoted e.g. `info!(user = "bob")`
+    /// matches `field_debug_eq("user", "\"bob\"")`.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let ids = asserter.field_debug_eq("ids", "[1, 2]");
+    /// tracing::info!(ids = ?[1, 2], "batch");
+    /// ids.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn field_debug_eq(&self, name: impl Into<String>, value: impl Into<String>) -> Assertion {
+        self.register(AssertionType::FieldDebug {
+            name: name.into(),
+            value: value.into(),
+        })
+    }
+    /// Creates an assertion that an event had a numeric field `name` less than `value`.
+    ///
+    /// Integer and float fields are compared as `f64`, other fields never match.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let fast = asserter.field_lt("latency_ms", 1000);
+    /// tracing::info!(latency_ms = 1200, "done");
+    /// (!&fast).assert();
+    /// tracing::info!(latency_ms = 800, "done");
+    /// fast.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn field_lt(&self, name: impl Into<String>, value: impl Into<f64>) -> Assertion {
+        self.field_cmp(name, CmpOp::Lt, value)
+    }
+    /// Creates an assertion that an event had a numeric field `name` less than or equal
+    /// to `value`, see [`Layer::field_lt`].
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn field_le(&self, name: impl Into<String>, value: impl Into<f64>) -> Assertion {
+        self.field_cmp(name, CmpOp::Le, value)
+    }
+    /// Creates an assertion that an event had a numeric field `name` greater than `value`,
+    /// see [`Layer::field_lt`].
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn field_gt(&self, name: impl Into<String>, value: impl Into<f64>) -> Assertion {
+        self.field_cmp(name, CmpOp::Gt, value)
+    }
+    /// Creates an assertion that an event had a numeric field `name` greater than or equal
+    /// to `value`, see [`Layer::field_lt`].
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn field_ge(&self, name: impl Into<String>, value: impl Into<f64>) -> Assertion {
+        self.field_cmp(name, CmpOp::Ge, value)
+    }
+    /// Creates an assertion that an event had a numeric field `name` equal to `value`,
+    /// see [`Layer::field_lt`].
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn field_eq(&self, name: impl Into<String>, value: impl Into<f64>) -> Assertion {
+        self.field_cmp(name, CmpOp::Eq, value)
+    }
+    /// Registers a new numeric field comparison assertion.
+    #[track_caller]
+    fn field_cmp(&self, name: impl Into<String>, op: CmpOp, value: impl Into<f64>) -> Assertion {
+        self.register(AssertionType::FieldCmp {
+            name: name.into(),
+            op,
+            value: value.into(),
+        })
+    }
+    /// Creates an assertion that an event had a bytes field containing `needle`.
+    ///
+    /// The field must have been recorded as bytes, e.g. `info!(buf = &bytes[..])`, fields
+    /// formatted with `?` or `%` never match. Unlike [`Layer::matches_hex`] this searches
+    /// the raw bytes rather than the message.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let header = asserter.bytes_field_contains("packet", &[0xca, 0xfe]);
+    /// let packet: &[u8] = &[0x00, 0xca, 0xfe, 0x01];
+    /// tracing::info!(packet, "sent");
+    /// header.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn bytes_field_contains(&self, name: impl Into<String>, needle: &[u8]) -> Assertion {
+        self.register(AssertionType::BytesContains {
+            name: name.into(),
+            needle: needle.to_vec(),
+        })
+    }
+    /// Creates an assertion that a message equal to any of `options` was logged.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let status = asserter.matches_one_of(&["ok", "degraded", "down"]);
+    /// tracing::info!("degraded");
+    /// status.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    #[track_caller]
+    pub fn matches_one_of(&self, options: &[&str]) -> Assertion {
+        self.register(AssertionType::OneOf(
+            options.iter().map(ToString::to_string).collect(),
+        ))
+    }
+    /// Creates an assertion that a message containing `bytes` hex encoded was logged.
+    ///
+    /// Bytes are encoded as lowercase hex digit pairs without separators, e.g.
+    /// `[0xde, 0xad]` is encoded as `dead`. This differs from how tracing renders bytes
+    /// fields, e.g. `[de ad]`, so those never match, use [`Layer::bytes_field_contains`]
+    /// for them instead.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let packet = asserter.matches_hex(&[0xca, 0xfe, 0x01]);
+    /// tracing::info!("sent cafe01");
+    /// packet.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When `bytes` is empty, since it would match every message, or the internal mutex
+    /// is poisoned.
+    #[must_use]
+    #[track_caller]
+    pub fn matches_hex(&self, bytes: &[u8]) -> Assertion {
+        use std::fmt::Write;
+        assert!(!bytes.is_empty(), "no bytes to match");
+        let hex = bytes.iter().fold(String::new(), |mut hex, byte| {
+            write!(hex, "{byte:02x}").unwrap();
+            hex
+        });
+        self.register(AssertionType::Contains(hex))
+    }
+    /// Creates an assertion that an event with the given fields occurred within spans with
+    /// the given fields.
+    ///
+    /// Fields are compared by their `Display` rendering, as in [`Layer::field_display_eq`].
+    /// The span fields may be spread across any of the spans the event is within, including
+    /// ancestors of the innermost span. This requires the subscriber to track spans, e.g.
+    /// [`tracing_subscriber::Registry`].
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let delete = asserter.correlate(&[("table", "users")], &[("op", "delete")]);
+    /// tracing::info_span!("query", table = "users").in_scope(|| {
+    ///     tracing::info!(op = "delete", "executing");
+    /// });
+    /// delete.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    #[track_caller]
+    pub fn correlate(
+        &self,
+        span_fields: &[(&str, &str)],
+        event_fields: &[(&str, &str)],
+    ) -> Assertion {
+        let owned = |fields: &[(&str, &str)]| {
+            fields
+                .iter()
+                .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+                .collect()
+        };
+        self.register(AssertionType::Correlate {
+            span_fields: owned(span_fields),
+            event_fields: owned(event_fields),
+        })
+    }
+    /// Creates an assertion that a span named `span` had the field `field` with the integer
+    /// value `value`.
+    ///
+    /// Values are compared by type as well as value, so this matches fields recorded as an
+    /// `i64` (e.g. integer literals) but not an `u64` or a string. Fields recorded after the
+    /// span is created with [`tracing::Span::record`] are included. This requires the
+    /// subscriber to track spans, e.g. [`tracing_subscriber::Registry`].
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let retries = asserter.span_field_i64("request", "retry_count", 3);
+    /// let span = tracing::info_span!("request", retry_count = tracing::field::Empty);
+    /// span.record("retry_count", 3);
+    /// retries.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn span_field_i64(
+        &self,
+        span: impl Into<String>,
+        field: impl Into<String>,
+        value: i64,
+    ) -> Assertion {
+        self.span_field(span, field, FieldValue::I64(value))
+    }
+    /// Creates an assertion that a span named `span` had the field `field` with the unsigned
+    /// integer value `value`, see [`Layer::span_field_i64`].
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn span_field_u64(
+        &self,
+        span: impl Into<String>,
+        field: impl Into<String>,
+        value: u64,
+    ) -> Assertion {
+        self.span_field(span, field, FieldValue::U64(value))
+    }
+    /// Creates an assertion that a span named `span` had the field `field` with the boolean
+    /// value `value`, see [`Layer::span_field_i64`].
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn span_field_bool(
+        &self,
+        span: impl Into<String>,
+        field: impl Into<String>,
+        value: bool,
+    ) -> Assertion {
+        self.span_field(span, field, FieldValue::Bool(value))
+    }
+    /// Creates an assertion that a span named `span` had the field `field` with the string
+    /// value `value`, see [`Layer::span_field_i64`].
+    ///
+    /// This only matches fields recorded as strings, not with the `%` or `?` sigils.
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn span_field_str(
+        &self,
+        span: impl Into<String>,
+        field: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Assertion {
+        self.span_field(span, field, FieldValue::Str(value.into()))
+    }
+    /// Registers a new span field assertion.
+    #[track_caller]
+    fn span_field(
+        &self,
+        span: impl Into<String>,
+        field: impl Into<String>,
+        value: FieldValue,
+    ) -> Assertion {
+        self.register(AssertionType::SpanField {
+            span: span.into(),
+            field: field.into(),
+            value,
+        })
+    }
+    /// Creates an assertion that a span named `name` was entered, e.g. the span of a
+    /// function annotated with `#[instrument]`.
+    ///
+    /// Span assertions are only matched against spans and event assertions only against
+    /// events, so `asserter.span_entered("a") & asserter.matches("b")` requires both a
+    /// span and an event. Like other assertions it passes when the layer is
+    /// [disabled](Layer::disable).
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let entered = asserter.span_entered("process_batch");
+    ///
+    /// #[tracing::instrument]
+    /// fn process_batch() {}
+    ///
+    /// (!&entered).assert();
+    /// process_batch();
+    /// entered.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn span_entered(&self, name: impl Into<String>) -> Assertion {
+        self.register(AssertionType::SpanEntered(name.into()))
+    }
+    /// Creates an assertion that an event with the message `s` and no other fields was logged.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let bare = asserter.is_bare_message("ready");
+    /// tracing::info!(port = 80, "ready");
+    /// assert!(!bool::from(&bare));
+    /// tracing::info!("ready");
+    /// bare.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn is_bare_message(&self, s: impl Into<String>) -> Assertion {
+        self.register(AssertionType::BareMessage(s.into()))
+    }
+    /// Creates an assertion that an event with the message `s` and at least one other field
+    /// was logged.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let structured = asserter.has_any_field("ready");
+    /// tracing::info!("ready");
+    /// assert!(!bool::from(&structured));
+    /// tracing::info!(port = 80, "ready");
+    /// structured.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn has_any_field(&self, s: impl Into<String>) -> Assertion {
+        self.register(AssertionType::HasAnyField(s.into()))
+    }
+    /// Creates an assertion that `s` appears anywhere in a rendered event.
+    ///
+    /// Events are rendered like `tracing_subscriber::fmt` does, as the message followed
+    /// by `key=value` for each other field, e.g. `info!(user = "bob", "login")` renders
+    /// as `login user=bob`. Where [`Layer::matches`] requires the message to equal `s`,
+    /// this matches any substring of that rendering, including field names and values.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let exact = asserter.matches("user=bob");
+    /// let anywhere = asserter.anywhere("user=bob");
+    /// tracing::info!(user = "bob", "login");
+    /// (!&exact).assert();
+    /// anywhere.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn anywhere(&self, s: impl Into<String>) -> Assertion {
+        self.register(AssertionType::Anywhere(s.into()))
+    }
+    /// Creates an assertion that an event was logged at exactly `level`.
+    ///
+    /// Combined with `&` the sides may be satisfied by different events, to require a
+    /// message be logged at a level use [`Assertion::at_level`].
+    ///
+    /// ```
+    /// use tracing::Level;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let failed = asserter.matches("connection failed") & asserter.level(Level::ERROR);
+    /// tracing::warn!("connection failed");
+    /// (!&failed).assert();
+    /// tracing::error!("giving up");
+    /// failed.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    #[track_caller]
+    pub fn level(&self, level: Level) -> Assertion {
+        self.register(AssertionType::Level(level))
+    }
+    /// Creates an assertion that any event was logged with the target `target`.
+    ///
+    /// Events default to the module path they were logged from. Combined with `&` the
+    /// sides may be satisfied by different events, to require a message be logged with a
+    /// target use [`Assertion::from_target`].
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let query = asserter.matches("query") & asserter.target("my_crate::db");
+    /// tracing::info!(target: "my_crate::http", "query");
+    /// (!&query).assert();
+    /// tracing::info!(target: "my_crate::db", "connected");
+    /// query.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn target(&self, target: impl Into<String>) -> Assertion {
+        self.register(AssertionType::Target(target.into()))
+    }
+    /// Creates an assertion that a message parsing as a number within `epsilon` of `n` was
+    /// logged.
+    ///
+    /// The message is trimmed then parsed as an [`f64`], so integers and floats in any
+    /// format Rust parses (e.g. `42`, `4.2e1`) match. Non-numeric messages never match.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let latency = asserter.matches_number(0.25, 0.01);
+    /// tracing::info!("{}", 0.2549);
+    /// latency.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    #[track_caller]
+    pub fn matches_number(&self, n: f64, epsilon: f64) -> Assertion {
+        self.register(AssertionType::Number { n, epsilon })
+    }
+    /// Creates an assertion that a message satisfying `f` was logged.
+    ///
+    /// This expresses conditions the other assertions can't. Failures show the pattern
+    /// as `<predicate>`.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let slow = asserter.predicate(|message| {
+    ///     message
+    ///         .strip_prefix("took ")
+    ///         .and_then(|ms| ms.strip_suffix("ms"))
+    ///         .and_then(|ms| ms.parse::<u32>().ok())
+    ///         .is_some_and(|ms| ms > 100)
+    /// });
+    /// asserter.check_message("took 20ms");
+    /// (!&slow).assert();
+    /// asserter.check_message("took 250ms");
+    /// slow.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn predicate<F>(&self, f: F) -> Assertion
+    where
+        F: Fn(&str) -> bool + Send + Sync + RefUnwindSafe + 'static,
+    {
+        self.register(AssertionType::Predicate(Predicate(Arc::new(f))))
+    }
+    /// Creates an ASCII case-insensitive string matching assertion.
+    ///
+    /// Only ASCII letters are compared case-insensitively, see
+    /// [`str::eq_ignore_ascii_case`]. For other scripts use [`Layer::matches_unicode_ci`].
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let not_found = asserter.matches_ignore_case("not found");
+    /// asserter.check_message("Not Found");
+    /// not_found.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn matches_ignore_case(&self, s: impl Into<String>) -> Assertion {
+        self.register(AssertionType::MatchesIgnoreCase(s.into()))
+    }
+    /// Creates a Unicode-aware case-insensitive string matching assertion.
+    ///
+    /// Both the pattern and the message are lowercased with [`str::to_lowercase`]
+    /// before comparison. With the `unicode-normalization` feature they are
+    /// additionally brought into Unicode Normalization Form C, so a message using
+    /// combining characters (e.g. `"e\u{301}"`) matches its composed equivalent
+    /// (e.g. `"é"`).
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let apples = asserter.matches_unicode_ci("ÄPFEL");
+    /// asserter.check_message("äpfel");
+    /// apples.assert();
+    /// ```
+    ///
+    /// With the `unicode-normalization` feature:
+    ///
+    #[cfg_attr(feature = "unicode-normalization", doc = "```")]
+    #[cfg_attr(not(feature = "unicode-normalization"), doc = "```ignore")]
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let cafe = asserter.matches_unicode_ci("CAFÉ");
+    /// asserter.check_message("cafe\u{301}");
+    /// cafe.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[track_caller]
+    pub fn matches_unicode_ci(&self, s: impl Into<String>) -> Assertion {
+        self.register(AssertionType::MatchesUnicodeCi(s.into()))
+    }
+    /// Creates a regex matching assertion.
+    ///
+    /// # Errors
+    ///
+    /// When the conversion to [`Regex`] fails.
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[cfg(feature = "regex")]
+    #[track_caller]
+    pub fn regex<T>(&self, s: T) -> Result<Assertion, <Regex as TryFrom<T>>::Error>
+    where
+        Regex: TryFrom<T>,
+    {
+        Ok(self.register(AssertionType::Regex(Regex::try_from(s)?)))
+    }
+    /// Creates a shell-style glob matching assertion.
+    ///
+    /// The whole message must match `pattern`, where `*` matches any run of characters,
+    /// `?` any one character and `[...]` one of a set e.g. `[a-z]` or `[!0-9]`. A `\`
+    /// matches the following character literally.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let completed = asserter.glob("request_*_completed").unwrap();
+    /// asserter.check_message("request_42_failed");
+    /// (!&completed).assert();
+    /// asserter.check_message("request_42_completed");
+    /// completed.assert();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// When `pattern` has an unclosed `[` or ends with `\`.
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[cfg(feature = "glob")]
+    #[track_caller]
+    pub fn glob(&self, pattern: impl Into<String>) -> Result<Assertion, GlobError> {
+        Ok(self.register(AssertionType::Glob(Glob::new(pattern.into())?)))
+    }
+    /// Creates an assertion that an event had a field whose `Display` rendering matches
+    /// the regex `pattern`.
+    ///
+    /// Events without the field never match.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let id = asserter.field_regex("request_id", "^[0-9a-f]{8}$").unwrap();
+    /// tracing::info!(request_id = "0badf00d", "handled");
+    /// id.assert();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// When `pattern` is not a valid regex.
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[cfg(feature = "regex")]
+    #[track_caller]
+    pub fn field_regex(
+        &self,
+        name: impl Into<String>,
+        pattern: &str,
+    ) -> Result<Assertion, regex::Error> {
+        Ok(self.register(AssertionType::FieldRegex {
+            name: name.into(),
+            regex: Regex::new(pattern)?,
+        }))
+    }
+    /// Creates an assertion that an event deserialized to a value equal to `expected`.
+    ///
+    /// The fields of the event, other than `message`, are deserialized as a JSON object into
+    /// `T`. When that fails the message is deserialized as JSON instead, which suits events
+    /// logging a JSON payload. Fields of the event not in `T` are ignored unless `T` denies
+    /// unknown fields, and fields with a mismatched type never match.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// #[derive(serde::Deserialize, PartialEq)]
+    /// struct Login {
+    ///     user: String,
+    ///     attempts: u32,
+    /// }
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let login = asserter.field_matches_typed(Login {
+    ///     user: String::from("bob"),
+    ///     attempts: 2,
+    /// });
+    /// tracing::info!(user = "bob", attempts = 2, "login");
+    /// login.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[cfg(feature = "serde")]
+    #[track_caller]
+    pub fn field_matches_typed<T>(&self, expected: T) -> Assertion
+    where
+        T: serde::de::DeserializeOwned + PartialEq + Send + Sync + RefUnwindSafe + 'static,
+    {
+        self.register(AssertionType::Typed(TypedMatcher {
+            type_name: std::any::type_name::<T>(),
+            matches: Arc::new(move |event| {
+                deserialize_event::<T>(event).is_some_and(|actual| actual == expected)
+            }),
+        }))
+    }
+    /// Creates a regex matching assertion which records the named capture groups of the
+    /// message it matches.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let login = asserter.regex_named_captures(r"user (?P<id>\d+) logged in").unwrap();
+    /// tracing::info!("user 42 logged in");
+    /// login.assert();
+    /// assert_eq!(login.capture("id").as_deref(), Some("42"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// When the conversion to [`Regex`] fails.
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[cfg(feature = "regex")]
+    #[track_caller]
+    pub fn regex_named_captures<T>(
+        &self,
+        s: T,
+    ) -> Result<NamedCaptureAssertion, <Regex as TryFrom<T>>::Error>
+    where
+        Regex: TryFrom<T>,
+    {
+        Ok(NamedCaptureAssertion(
+            self.register(AssertionType::Regex(Regex::try_from(s)?)),
+        ))
+    }
+    /// Creates a regex matching assertion which records the capture groups of the
+    /// message it matches.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let latency = asserter.regex_captures(r"took (\d+)ms").unwrap();
+    /// tracing::info!("took 27ms");
+    /// latency.assert_group(1, "27");
+    /// let ms = latency.group(1).unwrap().parse::<u32>().unwrap();
+    /// assert!(ms < 100);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// When the conversion to [`Regex`] fails.
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[cfg(feature = "regex")]
+    #[track_caller]
+    pub fn regex_captures<T>(&self, s: T) -> Result<CaptureAssertion, <Regex as TryFrom<T>>::Error>
+    where
+        Regex: TryFrom<T>,
+    {
+        Ok(CaptureAssertion(
+            self.register(AssertionType::Regex(Regex::try_from(s)?)),
+        ))
+    }
+    /// Creates an assertion that messages were logged in order.
+    ///
+    /// Messages between the steps of the sequence are ignored.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let sequence = asserter.sequence(["one", "two"]);
+    /// tracing::info!("one");
+    /// tracing::info!("three");
+    /// tracing::info!("two");
+    /// sequence.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    pub fn sequence(
+        &self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> SequenceAssertion {
+        self.sequence_of(patterns.into_iter().map(AssertionSpec::exact))
+    }
+    /// Creates an assertion that events satisfying each spec were logged in order.
+    ///
+    /// This generalizes [`Layer::sequence`] to any condition, see also [`log_script!`].
+    ///
+    /// ```
+    /// use tracing_assertions::AssertionSpec;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let sequence = asserter.sequence_of([
+    ///     AssertionSpec::exact("connecting"),
+    ///     AssertionSpec::exact("failed").with_level(tracing::Level::ERROR),
+    /// ]);
+    /// tracing::info!("connecting");
+    /// tracing::info!("failed");
+    /// tracing::error!("failed");
+    /// sequence.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    pub fn sequence_of(&self, specs: impl IntoIterator<Item = AssertionSpec>) -> SequenceAssertion {
+        let sequence = Arc::new(InnerSequence {
+            steps: specs.into_iter().collect(),
+            matched: Mutex::new(Vec::new()),
+        });
+        if !sequence.steps.is_empty() {
+            self.0
+                .resolve()
+                .sequences
+                .lock()
+                .unwrap()
+                .push(sequence.clone());
+        }
+        SequenceAssertion {
+            sequence,
+            asserter: self.0.clone(),
+        }
+    }
+    /// Creates an assertion on the first event processed by the layer.
+    ///
+    /// Unlike other assertions this is position based, the first event is recorded whatever
+    /// its message. Messages passed to [`Layer::check_message`] are not events.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// tracing::info!("starting");
+    /// tracing::warn!("running");
+    /// asserter
+    ///     .first_event()
+    ///     .message_eq("starting")
+    ///     .level_eq(tracing::Level::INFO);
+    /// ```
+    #[must_use]
+    pub fn first_event(&self) -> FirstEventAssertion {
+        FirstEventAssertion {
+            asserter: self.0.clone(),
+        }
+    }
+    /// Returns the messages of the events logged at `level`, in the order they were processed.
+    ///
+    /// This is always empty unless the layer was built with [`LayerBuilder::recording`].
+    /// Messages passed to [`Layer::check_message`] have no level, so are not recorded.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::builder().recording().build();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// tracing::info!("starting");
+    /// tracing::error!("disk full");
+    /// assert_eq!(asserter.recorded_at_level(tracing::Level::ERROR), ["disk full"]);
+    /// assert!(asserter.recorded_at_level(tracing::Level::WARN).is_empty());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn recorded_at_level(&self, level: Level) -> Vec<String> {
+        self.0
+            .recorded
+            .lock()
+            .unwrap()
+            .get(&level)
+            .cloned()
+            .unwrap_or_default()
+    }
+    /// Returns the messages of the events logged, in the order they were processed.
+    ///
+    /// Each message is followed by the other fields of the event, e.g. `login user=bob`,
+    /// so this shows everything logged when a test fails unexpectedly.
+    ///
+    /// This is always empty unless the layer was built with [`LayerBuilder::recording`].
+    /// Messages passed to [`Layer::check_message`] have no level, so are not recorded.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::builder().recording().build();
+    /// let guard = asserter.set_default();
+    /// tracing::info!(user = "bob", "login");
+    /// tracing::error!("disk full");
+    /// assert_eq!(asserter.recorded_messages(), ["login user=bob", "disk full"]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn recorded_messages(&self) -> Vec<String> {
+        let transcript = self.0.transcript.lock().unwrap();
+        transcript
+            .iter()
+            .map(|event| event.message.clone())
+            .collect()
+    }
+    /// Clears everything recorded so far, e.g. between the phases of a test.
+    ///
+    /// See [`LayerBuilder::recording`].
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::builder().recording().build();
+    /// let guard = asserter.set_default();
+    /// tracing::info!("setup");
+    /// asserter.clear_recorded();
+    /// tracing::info!("test");
+    /// assert_eq!(asserter.recorded_messages(), ["test"]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    pub fn clear_recorded(&self) {
+        self.0.recorded.lock().unwrap().clear();
+        self.0.transcript.lock().unwrap().clear();
+        self.0.targets.lock().unwrap().clear();
+        self.0.field_types.lock().unwrap().clear();
+    }
+    /// Resets every assertion on this layer and clears everything recorded, so the layer
+    /// can be reused as if new, e.g. between the cases of a table-driven test.
+    ///
+    /// Matched assertions are set back to false and re-registered, as
+    /// [`Assertion::reset`] would do for each, and the occurrences counted by pending ones,
+    /// see [`Layer::matches_times`], are set back to zero. Combined assertions hold their own
+    /// references to their leaves, so are reset along with them.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::builder().recording().build();
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// let both = &one & &two;
+    /// asserter.check_message("one");
+    /// asserter.check_message("two");
+    /// both.assert();
+    /// asserter.reset_all();
+    /// both.assert_false();
+    /// assert!(asserter.recorded_messages().is_empty());
+    /// asserter.check_message("one");
+    /// one.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    pub fn reset_all(&self) {
+        let consumed = std::mem::take(&mut *self.0.consumed.lock().unwrap());
+        let failed = std::mem::take(&mut *self.0.failed.lock().unwrap());
+        for assertion in consumed.iter().chain(&failed).filter_map(Weak::upgrade) {
+            self.0.rearm(&assertion);
+        }
+        for assertion in self.0.assertions.lock().unwrap().iter() {
+            assertion.occurrences.store(0, SeqCst);
+        }
+        self.0.matched.lock().unwrap().clear();
+        self.clear_recorded();
+    }
+    /// Returns the levels of the events logged, in the order they were processed.
+    ///
+    /// This is always empty unless the layer was built with [`LayerBuilder::recording`].
+    /// Messages passed to [`Layer::check_message`] have no level, so are not recorded.
+    ///
+    /// ```
+    /// use tracing::Level;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::builder().recording().build();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// tracing::info!("connecting");
+    /// tracing::warn!("retrying");
+    /// tracing::error!("giving up");
+    /// assert_eq!(asserter.level_sequence(), [Level::INFO, Level::WARN, Level::ERROR]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn level_sequence(&self) -> Vec<Level> {
+        let transcript = self.0.transcript.lock().unwrap();
+        transcript.iter().map(|event| event.level).collect()
+    }
+    /// Renders the events logged as a deterministic transcript for snapshot testing, e.g.
+    /// with `insta::assert_snapshot!(asserter.snapshot())`.
+    ///
+    /// Each event is a line of its level, target, message and fields, e.g.
+    /// ` INFO auth: login user=bob`, in the order they were processed. Newlines are escaped
+    /// as `\n` so each event stays on one line. Nothing varying between runs, like timestamps or thread
+    /// ids, is included.
+    ///
+    /// This is always empty unless the layer was built with [`LayerBuilder::recording`].
+    /// Messages passed to [`Layer::check_message`] have no level, so are not recorded.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::builder().recording().build();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// tracing::info!(target: "auth", user = "bob", "login");
+    /// tracing::error!(target: "db", "disk full");
+    /// assert_eq!(asserter.snapshot(), " INFO auth: login user=bob\nERROR db: disk full\n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn snapshot(&self) -> String {
+        self.0
+            .transcript
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| {
+                let message = event.message.replace('\n', "\\n");
+                format!("{:>5} {}: {message}\n", event.level, event.target)
+            })
+            .collect::<Vec<_>>()
+            .concat()
+    }
+    /// Returns the fraction of the last `window` events which were logged at `level` or a
+    /// more verbose level, e.g. for [`Level::DEBUG`] the `DEBUG` and `TRACE` events.
+    ///
+    /// The window is the last `window` events processed, or all of them when fewer have
+    /// been processed. With no events this is `0.0`. Messages passed to
+    /// [`Layer::check_message`] have no level, so are not counted.
+    ///
+    /// This is always `0.0` unless the layer was built with [`LayerBuilder::recording`].
+    ///
+    /// ```
+    /// use tracing::Level;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::builder().recording().build();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// tracing::warn!("starting");
+    /// for _ in 0..9 {
+    ///     tracing::debug!("polling");
+    /// }
+    /// tracing::info!("ready");
+    /// assert!(asserter.level_ratio(Level::DEBUG, 10) >= 0.9);
+    /// assert_eq!(asserter.level_ratio(Level::DEBUG, 5), 0.8);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)] // Windows are far smaller than 2^52 events.
+    pub fn level_ratio(&self, level: Level, window: usize) -> f64 {
+        let transcript = self.0.transcript.lock().unwrap();
+        let window = &transcript[transcript.len().saturating_sub(window)..];
+        if window.is_empty() {
+            return 0.0;
+        }
+        // More verbose levels compare greater.
+        let count = window.iter().filter(|event| event.level >= level).count();
+        count as f64 / window.len() as f64
+    }
+    /// Returns the distinct targets of the events logged, e.g. the modules which logged.
+    ///
+    /// This is always empty unless the layer was built with [`LayerBuilder::recording`].
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::builder().recording().build();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// tracing::info!(target: "auth", "login");
+    /// tracing::info!(target: "db", "query");
+    /// tracing::info!(target: "auth", "logout");
+    /// assert_eq!(asserter.targets_seen(), HashSet::from(["auth".into(), "db".into()]));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn targets_seen(&self) -> HashSet<String> {
+        self.0.targets.lock().unwrap().clone()
+    }
+    /// Asserts every event logged with the field `name` recorded it as `expected`.
+    ///
+    /// This passes when no event had the field.
+    ///
+    /// ```
+    /// use tracing_assertions::FieldType;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::builder().recording().build();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// tracing::info!(user_id = 7, "login");
+    /// tracing::info!(user_id = 8, "logout");
+    /// asserter.assert_field_type("user_id", FieldType::I64);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When an event recorded the field as another type, the layer was not built with
+    /// [`LayerBuilder::recording`] or the internal mutex is poisoned.
+    #[track_caller]
+    pub fn assert_field_type(&self, name: &str, expected: FieldType) {
+        if !CHECKED || self.0.pass_all.load(SeqCst) {
+            return;
+        }
+        assert!(
+            self.0.recording,
+            "field types are only recorded by a layer built with `LayerBuilder::recording`"
+        );
+        let field_types = self.0.field_types.lock().unwrap();
+        let Some(types) = field_types.get(name) else {
+            return;
+        };
+        let mut unexpected = types
+            .iter()
+            .filter(|field_type| **field_type != expected)
+            .collect::<Vec<_>>();
+        // Sorted since the iteration order of a `HashSet` is unspecified.
+        unexpected.sort();
+        assert!(
+            unexpected.is_empty(),
+            "expected {name:?} to be recorded as {expected:?} but it was also recorded as {unexpected:?}"
+        );
+    }
+    /// Checks a message against the registered assertions as if it had been logged.
+    ///
+    /// This allows driving assertions from messages which do not pass through
+    /// [`tracing`], e.g. lines captured from the stdout of a child process.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches("one");
+    /// asserter.check_message("one");
+    /// one.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    pub fn check_message(&self, message: &str) {
+        let processing = self.0.begin();
+        processing
+            .layer
+            .process(&EventRecord::from_message(message));
+    }
+    /// Registers a new assertion of the given type.
+    #[track_caller]
+    fn register(&self, assertion_type: AssertionType) -> Assertion {
+        self.register_spec(AssertionSpec::new(assertion_type))
+    }
+    /// Registers a new assertion.
+    #[track_caller]
+    fn register_spec(&self, spec: AssertionSpec) -> Assertion {
+        let inner_assertion = Arc::new(InnerAssertion::new(spec));
+        self.0.register(inner_assertion.clone());
+        Assertion(AssertionWrapper::One {
+            assertion: inner_assertion,
+            asserter: self.0.clone(),
+        })
+    }
+    /// Returns the patterns of the registered assertions which a message would match.
+    ///
+    /// This does not update any assertions. It is useful to find out which
+    /// pattern caught a message when an assertion unexpectedly passes.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// assert_eq!(asserter.explain("one"), ["one"]);
+    /// (!&one).assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn explain(&self, message: &str) -> Vec<String> {
+        let mut patterns = Vec::new();
+        let event = EventRecord::from_message(message);
+        for assertion in self.0.assertions.lock().unwrap().iter() {
+            if assertion.spec.matches(&event) {
+                let pattern = assertion.spec.assertion_type.to_string();
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+        }
+        patterns
+    }
+    /// Returns the specs of the registered assertions which have not yet matched.
+    ///
+    /// Combined with [`Layer::instantiate`] this allows defining a set of
+    /// assertions once and creating it against a fresh layer for each test case.
+    ///
+    /// ```
+    /// let template = tracing_assertions::Layer::default();
+    /// template.matches("one");
+    /// template.matches("two");
+    /// let specs = template.template();
+    ///
+    /// for _ in 0..2 {
+    ///     let asserter = tracing_assertions::Layer::default();
+    ///     let assertions = asserter.instantiate(&specs);
+    ///     asserter.check_message("one");
+    ///     asserter.check_message("two");
+    ///     for assertion in &assertions {
+    ///         assertion.assert();
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn template(&self) -> Vec<AssertionSpec> {
+        self.0
+            .assertions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|assertion| !assertion.boolean.load(SeqCst))
+            .map(|assertion| assertion.spec.clone())
+            .collect()
+    }
+    /// Creates a new assertion for each spec, see [`Layer::template`].
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    #[track_caller]
+    pub fn instantiate(&self, specs: &[AssertionSpec]) -> Vec<Assertion> {
+        let mut assertions = Vec::with_capacity(specs.len());
+        // A loop rather than `map` so `#[track_caller]` sees the caller.
+        for spec in specs {
+            assertions.push(self.register_spec(spec.clone()));
+        }
+        assertions
+    }
+    /// Returns the patterns of the assertions which are false.
+    ///
+    /// Assertions which have been dropped are not included. Assertions which can no longer
+    /// match, see [`Layer::expect_next`], follow the pending ones. Combined assertions are
+    /// evaluated as a whole and follow those, shown like `!error` or `(one && two)`, so a
+    /// negated assertion which holds is not failing.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// let no_error = !asserter.matches("error");
+    /// asserter.check_message("one");
+    /// assert_eq!(asserter.failing(), ["two"]);
+    /// asserter.check_message("error");
+    /// assert_eq!(asserter.failing(), ["two", "!error"]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn failing(&self) -> Vec<String> {
+        if self.0.pass_all.load(SeqCst) {
+            return Vec::new();
+        }
+        let mut failing = self
+            .0
+            .assertions
+            .lock()
+            .unwrap()
+            .iter()
+            // The layer holds the only reference to assertions which have been dropped.
+            .filter(|assertion| Arc::strong_count(assertion) > 1)
+            // Persistent assertions stay registered once matched.
+            .filter(|assertion| !assertion.boolean.load(SeqCst))
+            // The leaves of combined assertions are evaluated with their root below.
+            .filter(|assertion| assertion.root.lock().unwrap().is_none())
+            .map(|assertion| assertion.spec.assertion_type.to_string())
+            .collect::<Vec<_>>();
+        failing.extend(
+            self.0
+                .failed()
+                .iter()
+                .filter(|assertion| assertion.root.lock().unwrap().is_none())
+                .map(|assertion| assertion.spec.assertion_type.to_string()),
+        );
+        failing.extend(
+            self.0
+                .roots
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(Weak::upgrade)
+                .filter(|root| !root.holds())
+                .map(|root| root.to_string()),
+        );
+        failing
+    }
+    /// Renders a table of the registered assertions, with their pattern, type, whether
+    /// they have matched and how many events matched them.
+    ///
+    /// Assertions which have been dropped are not included. Assertions are unregistered
+    /// once matched unless [persistent](Assertion::persist), so this mostly lists the
+    /// pending ones. Failed assertions, see [`Layer::expect_next`], are listed last.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// // Kept alive, since dropped assertions are not reported.
+    /// let _one = asserter.matches("one");
+    /// let _answer = asserter.contains("42").persist();
+    /// asserter.check_message("the answer is 42");
+    /// assert_eq!(
+    ///     asserter.assertion_report(),
+    ///     "\
+    /// pattern | type     | state   | matches
+    /// --------+----------+---------+--------
+    /// one     | matches  | pending | 0
+    /// *42*    | contains | matched | 1"
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn assertion_report(&self) -> String {
+        let header = ["pattern", "type", "state", "matches"].map(String::from);
+        let registered = self
+            .0
+            .assertions
+            .lock()
+            .unwrap()
+            .iter()
+            // The layer holds the only reference to assertions which have been dropped.
+            .filter(|assertion| Arc::strong_count(assertion) > 1)
+            .cloned()
+            .collect::<Vec<_>>();
+        let rows = registered
+            .iter()
+            .chain(&self.0.failed())
+            .map(|assertion| {
+                let state = if assertion.boolean.load(SeqCst) {
+                    "matched"
+                } else if assertion.failed.load(SeqCst) {
+                    "failed"
+                } else {
+                    "pending"
+                };
+                [
+                    assertion.spec.assertion_type.to_string(),
+                    assertion.spec.assertion_type.kind().to_string(),
+                    state.to_string(),
+                    assertion.occurrences.load(SeqCst).to_string(),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let mut widths = header.clone().map(|column| column.chars().count());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let render = |row: &[String; 4]| {
+            row.iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join(" | ")
+                .trim_end()
+                .to_string()
+        };
+        let separator = widths.map(|width| "-".repeat(width)).join("-+-");
+        std::iter::once(render(&header))
+            .chain(std::iter::once(separator))
+            .chain(rows.iter().map(render))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    /// Registers a callback invoked with the message and the [`Assertion::id`] each time
+    /// an assertion matches.
+    ///
+    /// When an event matches multiple assertions the callback is invoked once
+    /// for each of them.
+    ///
+    /// The callback runs on the thread which emitted the event, as part of
+    /// processing it, so should be cheap.
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let matches = Arc::new(AtomicUsize::new(0));
+    /// let counter = matches.clone();
+    /// asserter.on_match(move |_, _| {
+    ///     counter.fetch_add(1, Ordering::SeqCst);
+    /// });
+    /// let one = asserter.matches("one");
+    /// tracing::info!("one");
+    /// assert_eq!(matches.load(Ordering::SeqCst), 1);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    pub fn on_match(&self, f: impl Fn(&str, u64) + Send + Sync + 'static) {
+        self.0.on_match.lock().unwrap().0.push(Arc::new(f));
+    }
+    /// Registers a callback invoked with the message each time a message equal to `s` is
+    /// processed.
+    ///
+    /// Unlike assertions, which match once until reset, this keeps matching for the life
+    /// of the layer.
+    ///
+    /// The callback runs on the thread which emitted the event, after the event has updated
+    /// the assertions and without holding the layer's locks, so it may use the layer.
+    /// Calls are serialized, so it must not emit a message equal to `s` itself, as that
+    /// would deadlock.
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let count = Arc::new(Mutex::new(0));
+    /// let counter = count.clone();
+    /// asserter.on_pattern("retrying", move |_| *counter.lock().unwrap() += 1);
+    /// tracing::info!("retrying");
+    /// tracing::info!("connected");
+    /// tracing::info!("retrying");
+    /// assert_eq!(*count.lock().unwrap(), 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    pub fn on_pattern(&self, s: impl Into<String>, f: impl FnMut(&str) + Send + 'static) {
+        self.0
+            .on_pattern
+            .lock()
+            .unwrap()
+            .0
+            .push((s.into(), Arc::new(Mutex::new(f))));
+    }
+    /// Moves the assertions and sequences registered with `other` to this layer.
+    ///
+    /// This allows building assertions with a separate layer, e.g. in a helper, then
+    /// evaluating them against the events of this layer.
+    ///
+    /// Afterwards `other` forwards to this layer: existing handles to assertions created by
+    /// `other` keep observing their moved assertions, resetting or cloning them registers
+    /// with this layer, as do new assertions created with `other`. [`Assertion::assert_within`]
+    /// waits on this layer's events. Events and spans processed by `other`, e.g. through a
+    /// subscriber it was installed in, and messages passed to its [`Layer::check_message`],
+    /// are processed by this layer. [`Layer::disable`] on `other` still applies to its
+    /// handles, and its [`Layer::on_match`] callbacks are not moved.
+    ///
+    /// Absorbing a layer into itself, directly or through other absorbed layers, does nothing.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let helper = tracing_assertions::Layer::default();
+    /// let connected = helper.matches("connected");
+    /// asserter.absorb(&helper);
+    /// tracing::info!("connected");
+    /// connected.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    pub fn absorb(&self, other: &Layer) {
+        let target = self.0.resolve();
+        let other = other.0.resolve();
+        if Arc::ptr_eq(&target, &other) {
+            return;
+        }
+        *other.absorbed_into.lock().unwrap() = Some(target.clone());
+        let assertions = other.assertions.lock().unwrap().take();
+        for assertion in assertions {
+            target.register(assertion);
+        }
+        let sequences = std::mem::take(&mut *other.sequences.lock().unwrap());
+        target.sequences.lock().unwrap().extend(sequences);
+        let failed = std::mem::take(&mut *other.failed.lock().unwrap());
+        target.failed.lock().unwrap().extend(failed);
+        let roots = std::mem::take(&mut *other.roots.lock().unwrap());
+        target.roots.lock().unwrap().extend(roots);
+    }
+    /// Waits for every event which other threads started processing before the call.
+    ///
+    /// Events are processed synchronously on the thread which emits them, so an event
+    /// whose emission *happens-before* the call (e.g. the emitting thread was joined) has
+    /// already been processed. An event can however be observed, e.g. by
+    /// [`Assertion::wait_blocking`] returning, while its thread is still processing it,
+    /// running its [`Layer::on_match`] callbacks. Once this returns every event, or span,
+    /// whose processing began on another thread before the call has been fully processed,
+    /// callbacks included, and its effects are visible to the calling thread.
+    ///
+    /// Called while the calling thread is processing an event, e.g. from a callback, this
+    /// only waits for the events which started before that event. Events started after
+    /// the call may be processed either before or after it returns.
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let notified = Arc::new(AtomicBool::new(false));
+    /// let flag = notified.clone();
+    /// asserter.on_match(move |_, _| {
+    ///     std::thread::sleep(Duration::from_millis(50));
+    ///     flag.store(true, Ordering::SeqCst);
+    /// });
+    /// let done = asserter.matches("done");
+    /// let worker = asserter.clone();
+    /// std::thread::spawn(move || worker.check_message("done"));
+    /// assert!(done.wait_blocking(Duration::from_secs(5)));
+    /// asserter.barrier();
+    /// assert!(notified.load(Ordering::SeqCst));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    pub fn barrier(&self) {
+        let layer = self.0.resolve();
+        let current = std::thread::current().id();
+        let in_flight = layer.in_flight.lock().unwrap();
+        // Waiting only on earlier events, callbacks can't wait on each other.
+        let before = in_flight
+            .active
+            .iter()
+            .find(|(_, thread)| **thread == current)
+            .map_or(in_flight.started, |(ticket, _)| *ticket);
+        drop(
+            layer
+                .landed
+                .wait_while(in_flight, |in_flight| {
+                    in_flight.active.range(..before).next().is_some()
+                })
+                .unwrap(),
+        );
+    }
+    /// Resets the matched assertions whenever a message equal to `marker` is processed.
+    ///
+    /// This marks the boundaries of phases in cyclic tests, e.g. a marker logged at the
+    /// start of each request lets the same assertions check each request. The reset
+    /// happens before the marker is matched against the assertions.
+    ///
+    /// Assertions are removed from the layer when they match, so only assertions which
+    /// match after this is called are reset. Each leaf of a combined assertion is reset
+    /// individually, as [`Assertion::rearm_matched`] would do.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// asserter.reset_on("request_start");
+    /// let handled = asserter.matches("handled");
+    /// asserter.check_message("request_start");
+    /// asserter.check_message("handled");
+    /// handled.assert();
+    /// asserter.check_message("request_start");
+    /// handled.assert_false();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    pub fn reset_on(&self, marker: impl Into<String>) {
+        self.0.reset_markers.lock().unwrap().push(marker.into());
+    }
+    /// Returns the ordinal of the last event processed, or `0` when none have been.
+    ///
+    /// Events (including messages passed to [`Layer::check_message`]) are numbered from
+    /// `1` in the order they are processed. Creating, recording to and entering a span
+    /// are numbered too, so span assertions can be compared against checkpoints. Compare against this with
+    /// [`Assertion::matched_before`] and [`Assertion::matched_after`] to relate when an
+    /// assertion matched to points in the test.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let written = asserter.matches("written");
+    /// asserter.check_message("written");
+    /// let checkpoint = asserter.checkpoint();
+    /// asserter.check_message("flushed");
+    /// assert!(written.matched_before(checkpoint));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn checkpoint(&self) -> u64 {
+        // Events are numbered while the lock is held.
+        let _assertions = self.0.assertions.lock().unwrap();
+        self.0.events.load(SeqCst)
+    }
+    /// Returns the number of events processed after the checkpoint `start` up to and
+    /// including the checkpoint `end`, see [`Layer::checkpoint`].
+    ///
+    /// Events which have not yet been processed are not counted, so an `end` past the
+    /// current checkpoint counts up to the current checkpoint.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let start = asserter.checkpoint();
+    /// asserter.check_message("one");
+    /// asserter.check_message("two");
+    /// let end = asserter.checkpoint();
+    /// assert_eq!(asserter.events_between(start, end), 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the internal mutex is poisoned.
+    #[must_use]
+    pub fn events_between(&self, start: u64, end: u64) -> usize {
+        let end = end.min(self.checkpoint());
+        usize::try_from(end.saturating_sub(start)).unwrap_or(usize::MAX)
+    }
+    /// Sets whether [`Assertion::assert`] records failures rather than panicking.
+    ///
+    /// In soft mode a failing assertion records where it was asserted and why, then
+    /// returns, so one run reports every failure. Call [`Layer::flush_failures`] at the
+    /// end of the test to panic with them.
+    ///
+    #[cfg_attr(not(feature = "debug-only"), doc = "```should_panic")]
+    #[cfg_attr(feature = "debug-only", doc = "```ignore")]
+    /// let asserter = tracing_assertions::Layer::default();
+    /// asserter.soft_mode(true);
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// one.assert();
+    /// two.assert();
+    /// asserter.flush_failures();
+    /// ```
+    pub fn soft_mode(&self, soft: bool) {
+        self.0.soft.store(soft, SeqCst);
+    }
+    /// Sets whether failures are shown in colour.
+    ///
+    /// By default colour is used when stdout is a terminal and the `NO_COLOR` environment
+    /// variable is unset, so logs captured by CI stay readable.
+    ///
+    #[cfg_attr(not(feature = "debug-only"), doc = "```")]
+    #[cfg_attr(feature = "debug-only", doc = "```ignore")]
+    /// let asserter = tracing_assertions::Layer::default();
+    /// asserter.set_color(false);
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// let both = &one & &two;
+    /// asserter.check_message("one");
+    /// let failure = std::panic::catch_unwind(|| both.assert()).unwrap_err();
+    /// let failure = failure.downcast_ref::<String>().unwrap();
+    /// assert!(failure.starts_with("(\"one\" && \"two\""));
+    /// ```
+    pub fn set_color(&self, enabled: bool) {
+        self.0.color.0.store(enabled, SeqCst);
+    }
+    /// Panics with the failures recorded in soft mode, clearing them.
+    ///
+    /// See [`Layer::soft_mode`].
+    ///
+    /// # Panics
+    ///
+    /// When any failures were recorded or the internal mutex is poisoned.
+    #[track_caller]
+    pub fn flush_failures(&self) {
+        let failures = std::mem::take(&mut *self.0.failures.lock().unwrap());
+        assert!(
+            failures.is_empty(),
+            "{} soft assertion failures:\n{}",
+            failures.len(),
+            failures.join("\n")
+        );
+    }
+    /// Evaluates every assertion, reporting all which are false together.
+    ///
+    /// Unlike asserting each in turn the first failure doesn't hide the rest. In
+    /// [soft mode](Layer::soft_mode) the failures are recorded rather than panicking.
+    ///
+    #[cfg_attr(not(feature = "debug-only"), doc = "```should_panic")]
+    #[cfg_attr(feature = "debug-only", doc = "```ignore")]
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// let three = asserter.matches("three");
+    /// asserter.check_message("two");
+    /// // Panics listing both "one" and "three".
+    /// asserter.assert_all(&[&one, &two, &three]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When any assertion is false, listing each on its own line.
+    #[track_caller]
+    pub fn assert_all(&self, assertions: &[&Assertion]) {
+        let failures = assertions
+            .iter()
+            .filter_map(|assertion| assertion.try_assert().err())
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        if failures.is_empty() {
+            return;
+        }
+        if self.0.soft.load(SeqCst) {
+            let location = std::panic::Location::caller();
+            self.0.failures.lock().unwrap().extend(
+                failures
+                    .iter()
+                    .map(|failure| format!("{location}: {failure}")),
+            );
+            return;
+        }
+        panic!(
+            "{} of {} assertions failed:\n{}",
+            failures.len(),
+            assertions.len(),
+            failures.join("\n")
+        );
+    }
+    /// The inverse of [`Layer::disable`].
+    pub fn enable(&self) {
+        self.0.pass_all.store(false, SeqCst);
+    }
+    /// Tells all assertions to pass.
+    ///
+    /// Useful when you want to disables certain tested logs in a
+    /// test for debugging without needing to comment out all the
+    /// assertions you added.
+    pub fn disable(&self) {
+        self.0.pass_all.store(true, SeqCst);
+    }
+    /// Calls `f` with all assertions passing, like [`Layer::disable`], then restores whether
+    /// they passed before.
+    ///
+    /// The previous state is restored even when `f` panics.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches("one");
+    /// asserter.muted(|| one.assert());
+    /// (!&one).assert();
+    /// ```
+    pub fn muted<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _guard = Muted {
+            previous: self.0.pass_all.swap(true, SeqCst),
+            asserter: &self.0,
+        };
+        f()
+    }
+}
+
+/// The events being processed by a layer, see [`Layer::barrier`].
+#[derive(Debug, Default)]
+struct InFlight {
+    /// The number of events which have started being processed.
+    started: u64,
+    /// The thread processing each event being processed, by the order it started in.
+    active: BTreeMap<u64, ThreadId>,
+}
+
+/// Marks an event as being processed until dropped, see [`Layer::barrier`].
+struct Processing {
+    layer: Arc<InnerLayer>,
+    ticket: u64,
+}
+
+impl Drop for Processing {
+    fn drop(&mut self) {
+        let mut in_flight = self.layer.in_flight.lock().unwrap();
+        in_flight.active.remove(&self.ticket);
+        drop(in_flight);
+        self.layer.landed.notify_all();
+    }
+}
+
+/// Restores [`InnerLayer::pass_all`] when dropped, see [`Layer::muted`].
+struct Muted<'a> {
+    asserter: &'a InnerLayer,
+    previous: bool,
+}
+
+impl Drop for Muted<'_> {
+    fn drop(&mut self) {
+        self.asserter.pass_all.store(self.previous, SeqCst);
+    }
+}
+
+#[derive(Debug, Clone)]
+enum AssertionType {
+    Matches(String),
+    MatchesStatic(&'static str),
+    Contains(String),
+    StartsWith(String),
+    EndsWith(String),
+    OneOf(HashSet<String>),
+    MatchesUnicodeCi(String),
+    MatchesIgnoreCase(String),
+    FieldDisplay {
+        name: String,
+        value: String,
+    },
+    FieldDebug {
+        name: String,
+        value: String,
+    },
+    FieldCmp {
+        name: String,
+        op: CmpOp,
+        value: f64,
+    },
+    BareMessage(String),
+    HasAnyField(String),
+    Anywhere(String),
+    Number {
+        n: f64,
+        epsilon: f64,
+    },
+    Level(Level),
+    Target(String),
+    Predicate(Predicate),
+    Correlate {
+        span_fields: Vec<(String, String)>,
+        event_fields: Vec<(String, String)>,
+    },
+    SpanEntered(String),
+    InSpan(String),
+    SpanField {
+        span: String,
+        field: String,
+        value: FieldValue,
+    },
+    BytesContains {
+        name: String,
+        needle: Vec<u8>,
+    },
+    #[cfg(feature = "regex")]
+    Regex(Regex),
+    #[cfg(feature = "glob")]
+    Glob(Glob),
+    #[cfg(feature = "regex")]
+    FieldRegex {
+        name: String,
+        regex: Regex,
+    },
+    #[cfg(feature = "serde")]
+    Typed(TypedMatcher),
+}
+
+/// A user condition on messages, see [`Layer::predicate`].
+#[derive(Clone)]
+struct Predicate(Arc<dyn Fn(&str) -> bool + Send + Sync + RefUnwindSafe>);
+
+impl Debug for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Predicate")
+    }
+}
+
+/// Matches events deserializing to an expected value, see [`Layer::field_matches_typed`].
+#[cfg(feature = "serde")]
+#[derive(Clone)]
+struct TypedMatcher {
+    type_name: &'static str,
+    matches: Arc<dyn Fn(&EventRecord) -> bool + Send + Sync + RefUnwindSafe>,
+}
+
+#[cfg(feature = "serde")]
+impl Debug for TypedMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TypedMatcher({})", self.type_name)
+    }
+}
+
+impl AssertionType {
+    /// Returns the name of the kind of assertion, see [`Layer::assertion_report`].
+    fn kind(&self) -> &'static str {
+        use AssertionType::*;
+        match self {
+            Matches(_) | MatchesStatic(_) => "matches",
+            Contains(_) => "contains",
+            StartsWith(_) => "starts_with",
+            EndsWith(_) => "ends_with",
+            OneOf(_) => "one_of",
+            MatchesUnicodeCi(_) => "unicode_ci",
+            MatchesIgnoreCase(_) => "ignore_case",
+            FieldDisplay { .. } => "field_display",
+            FieldDebug { .. } => "field_debug",
+            FieldCmp { .. } => "field_cmp",
+            BareMessage(_) => "bare_message",
+            HasAnyField(_) => "has_any_field",
+            Anywhere(_) => "anywhere",
+            Number { .. } => "number",
+            Level(_) => "level",
+            Target(_) => "target",
+            Predicate(_) => "predicate",
+            Correlate { .. } => "correlate",
+            SpanEntered(_) => "span_entered",
+            InSpan(_) => "in_span",
+            SpanField { .. } => "span_field",
+            BytesContains { .. } => "bytes_contains",
+            #[cfg(feature = "regex")]
+            Regex(_) => "regex",
+            #[cfg(feature = "glob")]
+            Glob(_) => "glob",
+            #[cfg(feature = "regex")]
+            FieldRegex { .. } => "field_regex",
+            #[cfg(feature = "serde")]
+            Typed(_) => "typed",
+        }
+    }
+}
+
+impl std::fmt::Display for AssertionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use AssertionType::*;
+        match self {
+            Matches(matches) | MatchesUnicodeCi(matches) | MatchesIgnoreCase(matches) => {
+                write!(f, "{matches}")
+            }
+            MatchesStatic(matches) => write!(f, "{matches}"),
+            Contains(contains) => write!(f, "*{contains}*"),
+            StartsWith(prefix) => write!(f, "{prefix}*"),
+            EndsWith(suffix) => write!(f, "*{suffix}"),
+            OneOf(options) => {
+                // Sorted since the iteration order of a `HashSet` is unspecified.
+                let mut options = options.iter().collect::<Vec<_>>();
+                options.sort();
+                write!(f, "one_of {options:?}")
+            }
+            FieldDisplay { name, value } => write!(f, "{name}=%{value}"),
+            FieldDebug { name, value } => write!(f, "{name}=?{value}"),
+            FieldCmp { name, op, value } => write!(f, "{name} {op} {value}"),
+            BareMessage(message) => write!(f, "{message} (bare)"),
+            HasAnyField(message) => write!(f, "{message} (with fields)"),
+            Anywhere(contains) => write!(f, "*{contains}* (anywhere)"),
+            Number { n, epsilon } => write!(f, "{n}±{epsilon}"),
+            Level(level) => write!(f, "level({level})"),
+            Target(target) => write!(f, "target({target})"),
+            Predicate(_) => write!(f, "<predicate>"),
+            Correlate {
+                span_fields,
+                event_fields,
+            } => {
+                let join = |fields: &[(String, String)]| {
+                    fields
+                        .iter()
+                        .map(|(name, value)| format!("{name}={value}"))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                };
+                write!(
+                    f,
+                    "span{{{}}} event{{{}}}",
+                    join(span_fields),
+                    join(event_fields)
+                )
+            }
+            BytesContains { name, needle } => write!(f, "{name} contains {}", hex_bytes(needle)),
+            SpanEntered(name) => write!(f, "entered({name})"),
+            InSpan(name) => write!(f, "in_span({name})"),
+            SpanField { span, field, value } => write!(f, "{span}.{field}={}", value.debug()),
+            #[cfg(feature = "regex")]
+            Regex(regex) => write!(f, "{regex}"),
+            #[cfg(feature = "glob")]
+            Glob(glob) => write!(f, "{}", glob.pattern),
+            #[cfg(feature = "regex")]
+            FieldRegex { name, regex } => write!(f, "{name}=~{regex}"),
+            #[cfg(feature = "serde")]
+            Typed(typed) => write!(f, "{}", typed.type_name),
+        }
+    }
+}
+
+/// An assertion.
+#[derive(Debug)]
+pub struct Assertion(AssertionWrapper);
+
+impl Clone for Assertion {
+    fn clone(&self) -> Self {
+        Self::rooted(self.0.clone())
+    }
+}
+
+/// Whether the events deciding an assertion have been logged, see [`Assertion::status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssertionStatus {
+    /// The outcome may still change as events are logged.
+    Pending,
+    /// Enough of the assertion has matched that its outcome is decided.
+    Matched,
+    /// Enough of the assertion can no longer match that its outcome is decided, see
+    /// [`Layer::expect_next`].
+    Failed,
+}
+
+/// This exists since there is no way of making enum variants private.
+enum AssertionWrapper {
+    And {
+        lhs: Box<Assertion>,
+        rhs: Box<Assertion>,
+        /// Whether this was built by [`Assertion::all`], so is shown as a flat list.
+        flat: bool,
+    },
+    Or {
+        lhs: Box<Assertion>,
+        rhs: Box<Assertion>,
+        /// Whether this was built by [`Assertion::any`], so is shown as a flat list.
+        flat: bool,
+    },
+    Xor {
+        lhs: Box<Assertion>,
+        rhs: Box<Assertion>,
+    },
+    Implies {
+        lhs: Box<Assertion>,
+        rhs: Box<Assertion>,
+    },
+    One {
+        assertion: Arc<InnerAssertion>,
+        asserter: Arc<InnerLayer>,
+    },
+    Not {
+        assertion: Box<Assertion>,
+    },
+    Labeled {
+        label: String,
+        inner: Box<Assertion>,
+    },
+}
+impl Debug for AssertionWrapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use AssertionWrapper::*;
+        match self {
+            One { assertion, .. } => {
+                let mut f = f.debug_struct("One");
+                f.field("id", &assertion.id)
+                    .field("pattern", &assertion.spec.assertion_type.to_string())
+                    .field("matched", &assertion.boolean.load(SeqCst));
+                #[cfg(feature = "creation-location")]
+                f.field("created_at", &assertion.created_at.to_string());
+                f.finish()
+            }
+            Not { assertion } => f.debug_struct("Not").field("assertion", assertion).finish(),
+            And { lhs, rhs, .. } => f
+                .debug_struct("And")
+                .field("lhs", lhs)
+                .field("rhs", rhs)
+                .finish(),
+            Or { lhs, rhs, .. } => f
+                .debug_struct("Or")
+                .field("lhs", lhs)
+                .field("rhs", rhs)
+                .finish(),
+            Xor { lhs, rhs } => f
+                .debug_struct("Xor")
+                .field("lhs", lhs)
+                .field("rhs", rhs)
+                .finish(),
+            Implies { lhs, rhs } => f
+                .debug_struct("Implies")
+                .field("lhs", lhs)
+                .field("rhs", rhs)
+                .finish(),
+            Labeled { label, inner } => f
+                .debug_struct("Labeled")
+                .field("label", label)
+                .field("inner", inner)
+                .finish(),
+        }
+    }
+}
+impl Clone for AssertionWrapper {
+    fn clone(&self) -> AssertionWrapper {
+        use AssertionWrapper::*;
+        match &self {
+            One {
+                assertion,
+                asserter,
+            } => {
+                let new_assertion = Arc::new(InnerAssertion {
+                    boolean: AtomicBool::from(assertion.boolean.load(SeqCst)),
+                    matched_at: Mutex::new(*assertion.matched_at.lock().unwrap()),
+                    matched_ordinal: Mutex::new(*assertion.matched_ordinal.lock().unwrap()),
+                    matched_thread: Mutex::new(*assertion.matched_thread.lock().unwrap()),
+                    captures: Mutex::new(assertion.captures.lock().unwrap().clone()),
+                    groups: Mutex::new(assertion.groups.lock().unwrap().clone()),
+                    occurrences: AtomicUsize::new(assertion.occurrences.load(SeqCst)),
+                    failed: AtomicBool::new(assertion.failed.load(SeqCst)),
+                    ..assertion.repeat()
+                });
+                // Like the original, a matched or failed assertion waits to be reset.
+                if new_assertion.failed.load(SeqCst) {
+                    asserter
+                        .failed
+                        .lock()
+                        .unwrap()
+                        .push(Arc::downgrade(&new_assertion));
+                } else if new_assertion.boolean.load(SeqCst)
+                    && !new_assertion.persistent.load(SeqCst)
+                {
+                    asserter.track_matched(&new_assertion);
+                } else {
+                    asserter.register(new_assertion.clone());
+                }
+                One {
+                    assertion: new_assertion,
+                    asserter: asserter.clone(),
+                }
+            }
+            Not { assertion } => Not {
+                assertion: assertion.clone(),
+            },
+            And { lhs, rhs, flat } => And {
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+                flat: *flat,
+            },
+            Or { lhs, rhs, flat } => Or {
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+                flat: *flat,
+            },
+            Xor { lhs, rhs } => Xor {
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+            },
+            Implies { lhs, rhs } => Implies {
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+            },
+            Labeled { label, inner } => Labeled {
+                label: label.clone(),
+                inner: inner.clone(),
+            },
+        }
+    }
+}
+
+impl Assertion {
+    /// Evaluates the assertion.
+    ///
+    /// In [soft mode](Layer::soft_mode) a failure is recorded rather than panicking.
+    ///
+    /// # Panics
+    ///
+    /// When the assertion is false.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert();` is ugly.
+    #[track_caller]
+    pub fn assert(&self) -> &Self {
+        if !CHECKED {
+            return self;
+        }
+        let Err(error) = self.try_assert() else {
+            return self;
+        };
+        let asserter = self.asserter().resolve();
+        if asserter.soft.load(SeqCst) {
+            let location = std::panic::Location::caller();
+            asserter
+                .failures
+                .lock()
+                .unwrap()
+                .push(format!("{location}: {error}"));
+            return self;
+        }
+        panic!("{error}");
+    }
+    /// Evaluates the assertion, returning an error rather than panicking when it is false.
+    ///
+    #[cfg_attr(not(feature = "debug-only"), doc = "```")]
+    #[cfg_attr(feature = "debug-only", doc = "```ignore")]
+    /// let asserter = tracing_assertions::Layer::default();
+    /// asserter.set_color(false);
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// asserter.check_message("one");
+    /// let failures = [&one, &two]
+    ///     .into_iter()
+    ///     .filter_map(|assertion| assertion.try_assert().err())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(failures.len(), 1);
+    /// assert!(failures[0].to_string().starts_with("\"two\""));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// When the assertion is false, the error shows the assertion as it would panic.
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    pub fn try_assert(&self) -> Result<&Self, AssertionError> {
+        if !CHECKED || bool::from(self) {
+            Ok(self)
+        } else {
+            Err(AssertionError(self.ansi()))
+        }
+    }
+    /// Evaluates the assertion then [resets](Assertion::reset) it.
+    ///
+    /// Equivalent to `assertion.assert().reset()`, for re-checking the same condition on
+    /// each iteration of a loop.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let tick = asserter.matches("tick");
+    /// for _ in 0..3 {
+    ///     tracing::info!("tick");
+    ///     tick.assert_and_reset();
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the assertion is false or the inner mutex is poisoned.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert_and_reset();` is ugly.
+    #[track_caller]
+    pub fn assert_and_reset(&self) -> &Self {
+        self.assert().reset();
+        self
+    }
+    /// Returns whether the assertion is false.
+    ///
+    /// This is equivalent to `!bool::from(&!&assertion)` without cloning the assertion
+    /// into a new `!` assertion, which registers a copy of every leaf with the layer.
+    /// Prefer this when checking a negation repeatedly, and `!` when the negation is to be
+    /// combined with other assertions.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches("one");
+    /// assert!(one.is_unsatisfied());
+    /// asserter.check_message("one");
+    /// assert!(!one.is_unsatisfied());
+    /// ```
+    #[must_use]
+    pub fn is_unsatisfied(&self) -> bool {
+        !bool::from(self)
+    }
+    /// Evaluates the assertion expecting it to be false.
+    ///
+    /// This reads clearer than `(!&assertion).assert()`.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let one = asserter.matches("one");
+    /// tracing::info!("two");
+    /// one.assert_false();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the assertion is true.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert_false();` is ugly.
+    #[track_caller]
+    pub fn assert_false(&self) -> &Self {
+        if !CHECKED {
+            return self;
+        }
+        assert!(
+            !bool::from(self),
+            "expected absence but matched: {}",
+            self.ansi()
+        );
+        self
+    }
+    /// Waits for the assertion to become true then evaluates it.
+    ///
+    /// This blocks until an event makes the assertion true or `timeout` elapses,
+    /// so suits assertions satisfied by events emitted from other threads.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let dispatch = tracing::Dispatch::new(tracing_subscriber::Registry::default().with(asserter.clone()));
+    /// let done = asserter.matches("done");
+    /// std::thread::spawn(move || {
+    ///     tracing::dispatcher::with_default(&dispatch, || tracing::info!("done"));
+    /// });
+    /// done.assert_within(Duration::from_secs(5));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the assertion is false after `timeout` or the inner mutex is poisoned.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert_within(y);` is ugly.
+    #[track_caller]
+    pub fn assert_within(&self, timeout: Duration) -> &Self {
+        if !CHECKED {
+            return self;
+        }
+        assert!(self.wait_blocking(timeout), "{}", self.ansi());
+        self
+    }
+    /// Waits for `duration` evaluating the assertion expecting it to stay false.
+    ///
+    /// This blocks for `duration`, failing as soon as an event makes the assertion true.
+    /// An assertion which is already true fails immediately.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let error = asserter.matches("error");
+    /// tracing::info!("steady");
+    /// error.assert_absent_for(Duration::from_millis(10));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the assertion becomes true within `duration` or the inner mutex is poisoned.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert_absent_for(y);` is ugly.
+    #[track_caller]
+    pub fn assert_absent_for(&self, duration: Duration) -> &Self {
+        if !CHECKED {
+            return self;
+        }
+        assert!(
+            !self.wait_blocking(duration),
+            "expected absence for {duration:?} but matched: {}",
+            self.ansi()
+        );
+        self
+    }
+    /// Blocks until the assertion is true or `timeout` elapses, returning its value.
+    ///
+    /// The thread sleeps on a condition variable notified after each event is processed,
+    /// so this does not busy-wait. This waits on the layer of the first leaf of the
+    /// assertion, see [`Assertion::assert_within`] to panic on timeout instead.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let done = asserter.matches("done");
+    /// let worker = asserter.clone();
+    /// let handle = std::thread::spawn(move || worker.check_message("done"));
+    /// assert!(done.wait_blocking(Duration::from_secs(5)));
+    /// handle.join().unwrap();
+    /// assert!(!asserter.matches("never").wait_blocking(Duration::from_millis(10)));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn wait_blocking(&self, timeout: Duration) -> bool {
+        self.wait_until(timeout, || bool::from(self))
+    }
+    /// Blocks until `n` events have matched the assertion or `timeout` elapses, returning
+    /// whether they did.
+    ///
+    /// Assertions are consumed by their first match, so only a
+    /// [persistent](Assertion::persist) assertion counts beyond one. Resetting the
+    /// assertion restarts the count.
+    ///
+    /// The count is updated while processing an event, with the layer's lock held, and is
+    /// read here with the lock held, so once this returns `true` the effects of the `n`
+    /// events on every assertion of the layer are visible to the calling thread, though
+    /// their [`Layer::on_match`] callbacks may still be running, see [`Layer::barrier`].
+    /// An event emitted on another thread is only certain to be counted when its emission
+    /// *happens-before* the check, e.g. the thread was joined; events emitted concurrently
+    /// are counted as they are processed, so more than `n` may have matched by the time
+    /// this returns.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let done = asserter.matches("done").persist();
+    /// let workers = (0..4)
+    ///     .map(|_| {
+    ///         let asserter = asserter.clone();
+    ///         std::thread::spawn(move || asserter.check_message("done"))
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// assert!(done.wait_for_count(4, Duration::from_secs(5)));
+    /// assert!(!done.wait_for_count(5, Duration::from_millis(10)));
+    /// # for worker in workers { worker.join().unwrap(); }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the assertion combines others, e.g. with `&`, or the inner mutex is poisoned.
+    #[must_use]
+    pub fn wait_for_count(&self, n: usize, timeout: Duration) -> bool {
+        let assertion = self.leaf();
+        self.wait_until(timeout, || assertion.occurrences.load(SeqCst) >= n)
+    }
+    /// Returns the single assertion this is, looking through labels.
+    fn leaf(&self) -> &Arc<InnerAssertion> {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One { assertion, .. } => assertion,
+            Labeled { inner, .. } => inner.leaf(),
+            Not { .. } | And { .. } | Or { .. } | Xor { .. } | Implies { .. } => {
+                panic!("expected a single assertion, not a combination")
+            }
+        }
+    }
+    /// Blocks until `done` returns true or `timeout` elapses, returning whether it did.
+    ///
+    /// `done` is checked with the lock of the layer of the first leaf held, and again
+    /// whenever the layer processes an event.
+    fn wait_until(&self, timeout: Duration, done: impl Fn() -> bool) -> bool {
+        let asserter = self.asserter().resolve();
+        let deadline = Instant::now() + timeout;
+        let mut assertions = asserter.assertions.lock().unwrap();
+        loop {
+            if done() {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            assertions = asserter
+                .processed
+                .wait_timeout(assertions, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+    /// Waits for the assertion to become true or `timeout` to elapse, returning its value.
+    ///
+    /// Unlike [`Assertion::assert_within`] this does not block the thread, so events
+    /// emitted by tasks on the same thread, e.g. with a `current_thread` tokio runtime,
+    /// are processed while waiting. The task is woken by the layer after each event and
+    /// by a timer thread at the deadline, so this works with any runtime. The timer thread
+    /// exits as soon as the wait finishes or is dropped.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// runtime.block_on(async {
+    ///     let done = asserter.matches("done");
+    ///     tokio::spawn(async { tracing::info!("done") });
+    ///     assert!(done.wait_yielding(Duration::from_secs(5)).await);
+    /// });
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[cfg(feature = "async")]
+    pub async fn wait_yielding(&self, timeout: Duration) -> bool {
+        let asserter = self.asserter().resolve();
+        let deadline = Instant::now() + timeout;
+        // Cancelled when dropped, so the timer thread exits once this finishes.
+        let mut timer: Option<Timer> = None;
+        std::future::poll_fn(|cx| {
+            // Held while evaluating so an event processed meanwhile wakes the task.
+            let mut wakers = asserter.wakers.lock().unwrap();
+            if bool::from(self) {
+                return Poll::Ready(true);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Poll::Ready(false);
+            }
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            match &timer {
+                Some(timer) => timer.update(cx.waker()),
+                None => timer = Some(Timer::start(deadline, cx.waker())),
+            }
+            Poll::Pending
+        })
+        .await
+    }
+    /// Waits for the assertion to become true, erroring if `timeout` elapses first.
+    ///
+    /// Like [`Assertion::wait_yielding`] this requires the `async` feature, or its `tokio`
+    /// alias, and works with any runtime.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// runtime.block_on(async {
+    ///     let done = asserter.matches("done");
+    ///     tokio::spawn(async { tracing::info!("done") });
+    ///     done.wait(Duration::from_secs(5)).await.unwrap();
+    /// });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// When the assertion is still false after `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[cfg(feature = "async")]
+    pub async fn wait(&self, timeout: Duration) -> Result<(), TimeoutError> {
+        if self.wait_yielding(timeout).await {
+            Ok(())
+        } else {
+            Err(TimeoutError(timeout))
+        }
+    }
+    /// Returns the smallest part of the assertion which makes it false, or `None` when it
+    /// is true.
+    ///
+    /// For `&` this is the failing part of the first false side, so a reporter can point
+    /// to the condition which broke. `!` and `|` can't be narrowed so are returned whole,
+    /// as are leaves.
+    ///
+    /// The returned assertion shares the state of this assertion, it does not register
+    /// new copies with the layer as cloning does.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// let three = asserter.matches("three");
+    /// let assertion = &one & (&two | &three);
+    /// asserter.check_message("one");
+    /// let failing = assertion.failing_subtree().unwrap();
+    /// assert!(format!("{failing:?}").starts_with("Assertion(Or"));
+    /// asserter.check_message("three");
+    /// assert!(assertion.failing_subtree().is_none());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn failing_subtree(&self) -> Option<Assertion> {
+        use AssertionWrapper::*;
+        if bool::from(self) {
+            return None;
+        }
+        match &self.0 {
+            And { lhs, rhs, .. } => lhs.failing_subtree().or_else(|| rhs.failing_subtree()),
+            One { .. } | Not { .. } | Or { .. } | Xor { .. } | Implies { .. } | Labeled { .. } => {
+                Some(self.share())
+            }
+        }
+    }
+    /// Creates an assertion, recording a combined assertion as the root of its leaves so
+    /// [`Layer::failing`] can evaluate it as a whole.
+    fn rooted(inner: AssertionWrapper) -> Assertion {
+        let assertion = Assertion(inner);
+        if let AssertionWrapper::One { .. } = assertion.0 {
+            return assertion;
+        }
+        let shape = Arc::new(assertion.shape());
+        let mut layers = Vec::<Arc<InnerLayer>>::new();
+        assertion.for_each_leaf(&mut |leaf, asserter| {
+            *leaf.root.lock().unwrap() = Some(shape.clone());
+            let layer = asserter.resolve();
+            if !layers.iter().any(|known| Arc::ptr_eq(known, &layer)) {
+                layers.push(layer);
+            }
+        });
+        for layer in layers {
+            layer.track_root(&shape);
+        }
+        assertion
+    }
+    /// Returns the shape of the assertion, referring to its leaves weakly.
+    fn shape(&self) -> Shape {
+        use AssertionWrapper::*;
+        let shape = |assertion: &Assertion| Box::new(assertion.shape());
+        match &self.0 {
+            One { assertion, .. } => Shape::One(Arc::downgrade(assertion)),
+            Not { assertion } => Shape::Not(shape(assertion)),
+            Labeled { label, inner } => Shape::Labeled(label.clone(), shape(inner)),
+            And { lhs, rhs, .. } => Shape::And(shape(lhs), shape(rhs)),
+            Or { lhs, rhs, .. } => Shape::Or(shape(lhs), shape(rhs)),
+            Xor { lhs, rhs } => Shape::Xor(shape(lhs), shape(rhs)),
+            Implies { lhs, rhs } => Shape::Implies(shape(lhs), shape(rhs)),
+        }
+    }
+    /// Calls `f` with each leaf of the assertion and the layer which created it.
+    fn for_each_leaf(&self, f: &mut impl FnMut(&Arc<InnerAssertion>, &Arc<InnerLayer>)) {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One {
+                assertion,
+                asserter,
+            } => f(assertion, asserter),
+            Not { assertion } => assertion.for_each_leaf(f),
+            Labeled { inner, .. } => inner.for_each_leaf(f),
+            And { lhs, rhs, .. }
+            | Or { lhs, rhs, .. }
+            | Xor { lhs, rhs }
+            | Implies { lhs, rhs } => {
+                lhs.for_each_leaf(f);
+                rhs.for_each_leaf(f);
+            }
+        }
+    }
+    /// Returns an assertion sharing the leaves of this assertion.
+    fn share(&self) -> Assertion {
+        use AssertionWrapper::*;
+        Assertion(match &self.0 {
+            One {
+                assertion,
+                asserter,
+            } => One {
+                assertion: assertion.clone(),
+                asserter: asserter.clone(),
+            },
+            Not { assertion } => Not {
+                assertion: Box::new(assertion.share()),
+            },
+            Labeled { label, inner } => Labeled {
+                label: label.clone(),
+                inner: Box::new(inner.share()),
+            },
+            And { lhs, rhs, flat } => And {
+                lhs: Box::new(lhs.share()),
+                rhs: Box::new(rhs.share()),
+                flat: *flat,
+            },
+            Or { lhs, rhs, flat } => Or {
+                lhs: Box::new(lhs.share()),
+                rhs: Box::new(rhs.share()),
+                flat: *flat,
+            },
+            Xor { lhs, rhs } => Xor {
+                lhs: Box::new(lhs.share()),
+                rhs: Box::new(rhs.share()),
+            },
+            Implies { lhs, rhs } => Implies {
+                lhs: Box::new(lhs.share()),
+                rhs: Box::new(rhs.share()),
+            },
+        })
+    }
+    /// Returns the id of the assertion.
+    ///
+    /// Ids are unique within the process, even between assertions with identical patterns,
+    /// which helps to tell apart assertions in [`Debug`] output and [`Layer::on_match`]
+    /// callbacks. Cloning or repeating an assertion creates a new assertion with a new id.
+    ///
+    /// A combination of assertions is identified by its leftmost leaf, which is unique to
+    /// it since combining assertions clones them.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches("one");
+    /// let other = asserter.matches("one");
+    /// assert_ne!(one.id(), other.id());
+    /// ```
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One { assertion, .. } => assertion.id,
+            Not { assertion } => assertion.id(),
+            Labeled { inner, .. } => inner.id(),
+            And { lhs, .. } | Or { lhs, .. } | Xor { lhs, .. } | Implies { lhs, .. } => lhs.id(),
+        }
+    }
+    /// Returns the layer of the first leaf of the assertion.
+    fn asserter(&self) -> &Arc<InnerLayer> {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One { asserter, .. } => asserter,
+            Not { assertion } => assertion.asserter(),
+            Labeled { inner, .. } => inner.asserter(),
+            And { lhs, .. } | Or { lhs, .. } | Xor { lhs, .. } | Implies { lhs, .. } => {
+                lhs.asserter()
+            }
+        }
+    }
+    /// Create a new assertion with the same condition.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let one = asserter.matches("one");
+    /// tracing::info!("one");
+    /// one.assert();
+    /// let one2 = one.repeat();
+    /// (!&one2).assert();
+    /// tracing::info!("one");
+    /// one2.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn repeat(&self) -> Self {
+        use AssertionWrapper::*;
+        let inner = match &self.0 {
+            One {
+                assertion,
+                asserter,
+            } => {
+                let new_assertion = Arc::new(assertion.repeat());
+                asserter.register(new_assertion.clone());
+                One {
+                    assertion: new_assertion,
+                    asserter: asserter.clone(),
+                }
+            }
+            Not { assertion } => Not {
+                assertion: Box::new(assertion.repeat()),
+            },
+            Labeled { label, inner } => Labeled {
+                label: label.clone(),
+                inner: Box::new(inner.repeat()),
+            },
+            And { lhs, rhs, flat } => And {
+                lhs: Box::new(lhs.repeat()),
+                rhs: Box::new(rhs.repeat()),
+                flat: *flat,
+            },
+            Or { lhs, rhs, flat } => Or {
+                lhs: Box::new(lhs.repeat()),
+                rhs: Box::new(rhs.repeat()),
+                flat: *flat,
+            },
+            Xor { lhs, rhs } => Xor {
+                lhs: Box::new(lhs.repeat()),
+                rhs: Box::new(rhs.repeat()),
+            },
+            Implies { lhs, rhs } => Implies {
+                lhs: Box::new(lhs.repeat()),
+                rhs: Box::new(rhs.repeat()),
+            },
+        };
+        Self::rooted(inner)
+    }
+
+    /// Resets the assertion.
+    ///
+    /// Only leaves which matched, or failed (see [`Layer::expect_next`]), are re-registered,
+    /// so pending leaves keep observing events. The occurrences counted by pending leaves,
+    /// see [`Layer::matches_times`], are set back to zero.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let one = asserter.matches("one");
+    /// tracing::info!("one");
+    /// one.assert().reset();
+    /// (!&one).assert();
+    /// tracing::info!("one");
+    /// one.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    pub fn reset(&self) {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One {
+                assertion,
+                asserter,
+            } => asserter.rearm(assertion),
+            Not { assertion } => assertion.reset(),
+            Labeled { inner, .. } => inner.reset(),
+            And { lhs, rhs, .. }
+            | Or { lhs, rhs, .. }
+            | Xor { lhs, rhs }
+            | Implies { lhs, rhs } => {
+                lhs.reset();
+                rhs.reset();
+            }
+        }
+    }
+
+    /// Removes the assertion from its layer, so later events are not checked against it.
+    ///
+    /// Assertions stay registered until they match, so unregistering those no longer
+    /// needed keeps processing events fast in long-running tests. The assertion keeps its
+    /// current value, and [resetting](Assertion::reset) it once matched registers it again.
+    ///
+    /// For `!`, `&`, `|`, `^` and [`Assertion::implies`] this applies to every leaf.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches("one");
+    /// one.unregister();
+    /// asserter.check_message("one");
+    /// one.assert_false();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    pub fn unregister(&self) {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One {
+                assertion,
+                asserter,
+            } => asserter.unregister(assertion),
+            Not { assertion } => assertion.unregister(),
+            Labeled { inner, .. } => inner.unregister(),
+            And { lhs, rhs, .. }
+            | Or { lhs, rhs, .. }
+            | Xor { lhs, rhs }
+            | Implies { lhs, rhs } => {
+                lhs.unregister();
+                rhs.unregister();
+            }
+        }
+    }
+
+    /// Keeps the assertion registered after it matches.
+    ///
+    /// By default an assertion is consumed by the first event which matches it, after
+    /// which it ignores events until [reset](Assertion::reset). A persistent assertion
+    /// stays true and keeps recording each later match, so [`Assertion::matched_at`],
+    /// [`NamedCaptureAssertion::capture`] and [`Layer::on_match`] callbacks reflect the most recent event.
+    /// Resetting it makes it false until the next match.
+    ///
+    /// For `!`, `&`, `|`, `^` and [`Assertion::implies`] this applies to every leaf.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let ready = asserter.matches("ready").persist();
+    /// tracing::info!("ready");
+    /// let first = ready.matched_at();
+    /// tracing::info!("ready");
+    /// assert!(ready.assert().matched_at() > first);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn persist(self) -> Self {
+        self.set_persistent();
+        self
+    }
+    fn set_persistent(&self) {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One {
+                assertion,
+                asserter,
+            } => {
+                // An already matched assertion was consumed so needs registering again.
+                if !assertion.persistent.swap(true, SeqCst) && assertion.boolean.load(SeqCst) {
+                    asserter.register(assertion.clone());
+                }
+            }
+            Not { assertion } => assertion.set_persistent(),
+            Labeled { inner, .. } => inner.set_persistent(),
+            And { lhs, rhs, .. }
+            | Or { lhs, rhs, .. }
+            | Xor { lhs, rhs }
+            | Implies { lhs, rhs } => {
+                lhs.set_persistent();
+                rhs.set_persistent();
+            }
+        }
+    }
+    /// Only match events logged at `level`, see [`AssertionSpec::with_level`].
+    ///
+    /// Filters are meant to be chained as the assertion is created, adding one discards
+    /// any earlier match.
+    ///
+    /// ```
+    /// use tracing::Level;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let denied = asserter
+    ///     .matches("denied")
+    ///     .at_level(Level::ERROR)
+    ///     .from_target("auth");
+    /// tracing::error!(target: "db", "denied");
+    /// tracing::warn!(target: "auth", "denied");
+    /// (!&denied).assert();
+    /// tracing::error!(target: "auth", "denied");
+    /// denied.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn at_level(self, level: Level) -> Self {
+        self.refine(&|spec| spec.with_level(level))
+    }
+    /// Only match events logged with the target `target`, see [`AssertionSpec::from_target`].
+    ///
+    /// Like [`Assertion::at_level`], adding the filter discards any earlier match.
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn from_target(self, target: impl Into<String>) -> Self {
+        let target = target.into();
+        self.refine(&|spec| spec.from_target(target.clone()))
+    }
+    /// Replaces every leaf with an unmatched assertion with the spec updated by `f`.
+    fn refine(self, f: &dyn Fn(AssertionSpec) -> AssertionSpec) -> Self {
+        use AssertionWrapper::*;
+        let inner = match self.0 {
+            One {
+                assertion,
+                asserter,
+            } => {
+                // The spec decides how the assertion is indexed, so it is registered anew.
+                let refined = Arc::new(InnerAssertion {
+                    id: assertion.id,
+                    persistent: AtomicBool::new(assertion.persistent.load(SeqCst)),
+                    #[cfg(feature = "creation-location")]
+                    created_at: assertion.created_at,
+                    ..InnerAssertion::new(f(assertion.spec.clone()))
+                });
+                asserter.unregister(&assertion);
+                asserter.register(refined.clone());
+                One {
+                    assertion: refined,
+                    asserter,
+                }
+            }
+            Not { assertion } => Not {
+                assertion: Box::new(assertion.refine(f)),
+            },
+            Labeled { label, inner } => Labeled {
+                label,
+                inner: Box::new(inner.refine(f)),
+            },
+            And { lhs, rhs, flat } => And {
+                lhs: Box::new(lhs.refine(f)),
+                rhs: Box::new(rhs.refine(f)),
+                flat,
+            },
+            Or { lhs, rhs, flat } => Or {
+                lhs: Box::new(lhs.refine(f)),
+                rhs: Box::new(rhs.refine(f)),
+                flat,
+            },
+            Xor { lhs, rhs } => Xor {
+                lhs: Box::new(lhs.refine(f)),
+                rhs: Box::new(rhs.refine(f)),
+            },
+            Implies { lhs, rhs } => Implies {
+                lhs: Box::new(lhs.refine(f)),
+                rhs: Box::new(rhs.refine(f)),
+            },
+        };
+        Self::rooted(inner)
+    }
+
+    /// Creates an assertion that if this assertion is true then `other` is too.
+    ///
+    /// This is `!self | other`, but reads as intended and shows as `(self => other)`.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let opened = asserter.matches("opened");
+    /// let closed = asserter.matches("closed");
+    /// let balanced = opened.implies(&closed);
+    /// balanced.assert();
+    /// asserter.check_message("opened");
+    /// (!&balanced).assert();
+    /// asserter.check_message("closed");
+    /// balanced.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn implies(&self, other: &Assertion) -> Assertion {
+        Assertion::rooted(AssertionWrapper::Implies {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(other.clone()),
+        })
+    }
+    /// Creates an assertion which is true when all of `assertions` are true.
+    ///
+    /// This is equivalent to combining them with `&`, without the precedence of the
+    /// operators to keep in mind, and is shown as `(a && b && c)`. Returns `None` when
+    /// `assertions` is empty, since an assertion belongs to a layer there is no assertion
+    /// which is always true.
+    ///
+    /// ```
+    /// use tracing_assertions::Assertion;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// let three = asserter.matches("three");
+    /// let all = Assertion::all([&one, &two, &three]).unwrap();
+    /// asserter.check_message("one");
+    /// asserter.check_message("two");
+    /// all.assert_false();
+    /// asserter.check_message("three");
+    /// all.assert();
+    /// assert!(Assertion::all([]).is_none());
+    /// ```
+    #[must_use]
+    pub fn all<'a>(assertions: impl IntoIterator<Item = &'a Assertion>) -> Option<Assertion> {
+        let assertions = assertions.into_iter().collect::<Vec<_>>();
+        Self::balanced(&assertions, |lhs, rhs| AssertionWrapper::And {
+            lhs,
+            rhs,
+            flat: true,
+        })
+    }
+    /// Creates an assertion which is true when any of `assertions` are true.
+    ///
+    /// This is equivalent to combining them with `|`, and is shown as `(a || b || c)`.
+    /// Returns `None` when `assertions` is empty, since an assertion belongs to a layer
+    /// there is no assertion which is always false.
+    ///
+    /// ```
+    /// use tracing_assertions::Assertion;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let timeout = asserter.matches("timeout");
+    /// let refused = asserter.matches("refused");
+    /// let any = Assertion::any([&timeout, &refused]).unwrap();
+    /// any.assert_false();
+    /// asserter.check_message("refused");
+    /// any.assert();
+    /// assert!(Assertion::any([]).is_none());
+    /// ```
+    #[must_use]
+    pub fn any<'a>(assertions: impl IntoIterator<Item = &'a Assertion>) -> Option<Assertion> {
+        let assertions = assertions.into_iter().collect::<Vec<_>>();
+        Self::balanced(&assertions, |lhs, rhs| AssertionWrapper::Or {
+            lhs,
+            rhs,
+            flat: true,
+        })
+    }
+    /// Returns whether the events deciding this assertion have been logged.
+    ///
+    /// This distinguishes an assertion which is false since nothing has been logged yet
+    /// from one which is false since the wrong thing was logged. A single assertion is
+    /// [`AssertionStatus::Matched`] once it matches, and a negated assertion has the status
+    /// of the assertion it negates. For `&` and `^` both sides must have matched, for `|`
+    /// either side and for [`Assertion::implies`] the consequent. A failed
+    /// [`Layer::expect_next`] is [`AssertionStatus::Failed`], which decides `&` when either
+    /// side has failed and `|` when both have.
+    ///
+    /// ```
+    /// use tracing_assertions::AssertionStatus;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let started = asserter.matches("started");
+    /// let failed = asserter.matches("failed");
+    /// let ok = &started & !&failed;
+    /// assert_eq!(ok.status(), AssertionStatus::Pending);
+    /// asserter.check_message("started");
+    /// assert_eq!(ok.status(), AssertionStatus::Pending);
+    /// asserter.check_message("failed");
+    /// assert_eq!(ok.status(), AssertionStatus::Matched);
+    /// ok.assert_false();
+    /// ```
+    #[must_use]
+    pub fn status(&self) -> AssertionStatus {
+        use AssertionStatus::{Failed, Matched, Pending};
+        use AssertionWrapper::*;
+        match &self.0 {
+            One { assertion, .. } => {
+                if assertion.failed.load(SeqCst) {
+                    Failed
+                } else if assertion.boolean.load(SeqCst) {
+                    Matched
+                } else {
+                    Pending
+                }
+            }
+            Not { assertion } => assertion.status(),
+            Labeled { inner, .. } => inner.status(),
+            And { lhs, rhs, .. } => match (lhs.status(), rhs.status()) {
+                (Failed, _) | (_, Failed) => Failed,
+                (Matched, Matched) => Matched,
+                _ => Pending,
+            },
+            Or { lhs, rhs, .. } => match (lhs.status(), rhs.status()) {
+                (Matched, _) | (_, Matched) => Matched,
+                (Failed, Failed) => Failed,
+                _ => Pending,
+            },
+            Xor { lhs, rhs } => match (lhs.status(), rhs.status()) {
+                (Pending, _) | (_, Pending) => Pending,
+                (Failed, Failed) => Failed,
+                _ => Matched,
+            },
+            Implies { rhs, .. } => rhs.status(),
+        }
+    }
+    /// Labels the assertion, so failures show which check it belongs to, e.g.
+    /// `[db-connect] "connected"`.
+    ///
+    /// The label does not change the assertion, evaluating, repeating or resetting it
+    /// acts on the labeled assertion.
+    ///
+    #[cfg_attr(not(feature = "debug-only"), doc = "```")]
+    #[cfg_attr(feature = "debug-only", doc = "```ignore")]
+    /// let asserter = tracing_assertions::Layer::default();
+    /// asserter.set_color(false);
+    /// let connected = asserter.matches("connected").labeled("db-connect");
+    /// let error = connected.try_assert().unwrap_err();
+    /// assert!(error.to_string().starts_with("[db-connect] \"connected\""));
+    /// asserter.check_message("connected");
+    /// connected.assert();
+    /// ```
+    #[must_use]
+    pub fn labeled(self, label: impl Into<String>) -> Assertion {
+        Assertion::rooted(AssertionWrapper::Labeled {
+            label: label.into(),
+            inner: Box::new(self),
+        })
+    }
+    /// Returns when the assertion was last satisfied by an event.
+    ///
+    /// For `&` this is when the later of both sides matched, for `|` when the
+    /// earlier side matched, for `^` when the only side matched and for
+    /// [`Assertion::implies`] when the consequent matched. A negated assertion has no
+    /// match time.
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn matched_at(&self) -> Option<Instant> {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One { assertion, .. } => *assertion.matched_at.lock().unwrap(),
+            Not { .. } => None,
+            Labeled { inner, .. } => inner.matched_at(),
+            And { lhs, rhs, .. } => Some(lhs.matched_at()?.max(rhs.matched_at()?)),
+            Or { lhs, rhs, .. } => match (lhs.matched_at(), rhs.matched_at()) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+            Xor { lhs, rhs } => match (lhs.matched_at(), rhs.matched_at()) {
+                (Some(_), Some(_)) => None,
+                (a, b) => a.or(b),
+            },
+            Implies { rhs, .. } => rhs.matched_at(),
+        }
+    }
+
+    /// Returns whether the events which satisfied this assertion and `other` were logged
+    /// on the same thread, or `None` when either has not matched.
+    ///
+    /// For `&`, `|` and `^` this uses the same event as [`Assertion::matched_at`]. A negated
+    /// assertion has no match, so this returns `None`.
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let enter = asserter.matches("enter");
+    /// let exit = asserter.matches("exit");
+    /// assert_eq!(enter.same_thread_as(&exit), None);
+    /// tracing::info!("enter");
+    /// tracing::info!("exit");
+    /// assert_eq!(enter.same_thread_as(&exit), Some(true));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn same_thread_as(&self, other: &Assertion) -> Option<bool> {
+        Some(self.matched_thread()? == other.matched_thread()?)
+    }
+    fn matched_thread(&self) -> Option<ThreadId> {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One { assertion, .. } => *assertion.matched_thread.lock().unwrap(),
+            Not { .. } => None,
+            Labeled { inner, .. } => inner.matched_thread(),
+            And { lhs, rhs, .. } => {
+                if lhs.matched_at()? >= rhs.matched_at()? {
+                    lhs.matched_thread()
+                } else {
+                    rhs.matched_thread()
+                }
+            }
+            Or { lhs, rhs, .. } => match (lhs.matched_at(), rhs.matched_at()) {
+                (Some(a), Some(b)) if b < a => rhs.matched_thread(),
+                (Some(_), _) => lhs.matched_thread(),
+                (None, _) => rhs.matched_thread(),
+            },
+            Xor { lhs, rhs } => match (lhs.matched_at(), rhs.matched_at()) {
+                (Some(_), None) => lhs.matched_thread(),
+                (None, Some(_)) => rhs.matched_thread(),
+                _ => None,
+            },
+            Implies { rhs, .. } => rhs.matched_thread(),
+        }
+    }
+
+    /// Returns whether the assertion was satisfied by an event processed at or before
+    /// `checkpoint`, see [`Layer::checkpoint`].
+    ///
+    /// For `&`, `|` and `^` this uses the same event as [`Assertion::matched_at`]. A negated
+    /// assertion has no match, so this returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn matched_before(&self, checkpoint: u64) -> bool {
+        self.matched_ordinal()
+            .is_some_and(|ordinal| ordinal <= checkpoint)
+    }
+
+    /// Returns whether the assertion was satisfied by an event processed after
+    /// `checkpoint`, see [`Layer::checkpoint`].
+    ///
+    /// For `&`, `|` and `^` this uses the same event as [`Assertion::matched_at`]. A negated
+    /// assertion has no match, so this returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn matched_after(&self, checkpoint: u64) -> bool {
+        self.matched_ordinal()
+            .is_some_and(|ordinal| ordinal > checkpoint)
+    }
+
+    /// Asserts both assertions were satisfied and this one by an earlier event than `other`.
+    ///
+    /// Events are compared by the order they were processed, see [`Layer::checkpoint`].
+    /// For `&`, `|` and `^` this uses the same event as [`Assertion::matched_at`].
+    ///
+    /// ```
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let guard = asserter.set_default();
+    /// let connect = asserter.matches("connect");
+    /// let query = asserter.matches("query");
+    /// tracing::info!("connect");
+    /// tracing::info!("query");
+    /// connect.assert_before(&query);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When either assertion was not satisfied, this one was satisfied by the same or a
+    /// later event than `other`, or the inner mutex is poisoned.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert_before(y);` is ugly.
+    #[track_caller]
+    pub fn assert_before(&self, other: &Assertion) -> &Self {
+        if !CHECKED || self.asserter().pass_all.load(SeqCst) {
+            return self;
+        }
+        let (this, that) = (self.matched_ordinal(), other.matched_ordinal());
+        let event = |ordinal: Option<u64>| {
+            ordinal.map_or_else(
+                || "not matched".to_string(),
+                |ordinal| format!("event {ordinal}"),
+            )
+        };
+        assert!(
+            matches!((this, that), (Some(this), Some(that)) if this < that),
+            "expected {} ({}) before {} ({})",
+            self.ansi(),
+            event(this),
+            other.ansi(),
+            event(that)
+        );
+        self
+    }
+
+    /// Returns the ordinal of the event which last satisfied the assertion.
+    fn matched_ordinal(&self) -> Option<u64> {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One { assertion, .. } => *assertion.matched_ordinal.lock().unwrap(),
+            Not { .. } => None,
+            Labeled { inner, .. } => inner.matched_ordinal(),
+            And { lhs, rhs, .. } => Some(lhs.matched_ordinal()?.max(rhs.matched_ordinal()?)),
+            Or { lhs, rhs, .. } => match (lhs.matched_ordinal(), rhs.matched_ordinal()) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+            Xor { lhs, rhs } => match (lhs.matched_ordinal(), rhs.matched_ordinal()) {
+                (Some(_), Some(_)) => None,
+                (a, b) => a.or(b),
+            },
+            Implies { rhs, .. } => rhs.matched_ordinal(),
+        }
+    }
+
+    /// Resets only the leaves of the assertion which have matched.
+    ///
+    /// Both this and [`Assertion::reset`] leave pending leaves registered, only setting
+    /// back their counted occurrences, but where
+    /// [`Assertion::reset`] also re-arms leaves which failed (see [`Layer::expect_next`]),
+    /// this leaves them failed. This is useful to re-verify events which were already seen
+    /// without giving failed expectations another chance.
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let one = asserter.matches("one");
+    /// let two = asserter.matches("two");
+    /// let and = &one & &two;
+    /// tracing::info!("one");
+    /// and.rearm_matched();
+    /// tracing::info!("two");
+    /// (!&and).assert();
+    /// tracing::info!("one");
+    /// and.assert();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    pub fn rearm_matched(&self) {
+        use AssertionWrapper::*;
+        match &self.0 {
+            One {
+                assertion,
+                asserter,
+            } => asserter.rearm_matched(assertion),
+            Not { assertion } => assertion.rearm_matched(),
+            Labeled { inner, .. } => inner.rearm_matched(),
+            And { lhs, rhs, .. }
+            | Or { lhs, rhs, .. }
+            | Xor { lhs, rhs }
+            | Implies { lhs, rhs } => {
+                lhs.rearm_matched();
+                rhs.rearm_matched();
+            }
+        }
+    }
+
+    fn ansi(&self) -> String {
+        use AssertionWrapper::*;
+
+        match &self.0 {
+            One {
+                assertion,
+                asserter,
+            } => {
+                let is_true = if asserter.pass_all.load(SeqCst) {
+                    true
+                } else {
+                    assertion.boolean.load(std::sync::atomic::Ordering::SeqCst)
+                };
+                let str = format!("{:?}", assertion.spec.assertion_type.to_string());
+                if is_true {
+                    return asserter.paint(ansi_term::Colour::Green, str);
+                }
+                let out = asserter.paint(ansi_term::Colour::Red, str);
+                #[cfg(feature = "creation-location")]
+                return format!("{out} (created at {})", assertion.created_at);
+                #[cfg(not(feature = "creation-location"))]
+                out
+            }
+            And {
+                lhs,
+                rhs,
+                flat: false,
+            } => format!("({} && {})", lhs.ansi(), rhs.ansi()),
+            Or {
+                lhs,
+                rhs,
+                flat: false,
+            } => format!("({} || {})", lhs.ansi(), rhs.ansi()),
+            And { flat: true, .. } => {
+                let mut operands = Vec::new();
+                self.chain(&mut operands, AssertionWrapper::and_operands);
+                format!("({})", operands.join(" && "))
+            }
+            Or { flat: true, .. } => {
+                let mut operands = Vec::new();
+                self.chain(&mut operands, AssertionWrapper::or_operands);
+                format!("({})", operands.join(" || "))
+            }
+            Xor { lhs, rhs } => format!("({} ^ {})", lhs.ansi(), rhs.ansi()),
+            Implies { lhs, rhs } => format!("({} => {})", lhs.ansi(), rhs.ansi()),
+            Not { assertion } => format!("!{}", assertion.ansi()),
+            Labeled { label, inner } => format!("[{label}] {}", inner.ansi()),
+        }
+    }
+    /// Renders the operands of a tree built by [`Assertion::all`] or [`Assertion::any`],
+    /// so `Assertion::all([a, b, c])` is shown as `(a && b && c)`.
+    fn chain(
+        &self,
+        operands: &mut Vec<String>,
+        split: fn(&AssertionWrapper) -> Option<(&Assertion, &Assertion)>,
+    ) {
+        match split(&self.0) {
+            Some((lhs, rhs)) => {
+                lhs.chain(operands, split);
+                rhs.chain(operands, split);
+            }
+            None => operands.push(self.ansi()),
+        }
+    }
+    /// Combines assertions into a balanced tree with `combine`, returning `None` when
+    /// there are none.
+    fn balanced(
+        assertions: &[&Assertion],
+        combine: fn(Box<Assertion>, Box<Assertion>) -> AssertionWrapper,
+    ) -> Option<Assertion> {
+        match assertions {
+            [] => None,
+            [assertion] => Some((*assertion).clone()),
+            _ => {
+                let (lhs, rhs) = assertions.split_at(assertions.len() / 2);
+                Some(Assertion::rooted(combine(
+                    Box::new(Self::balanced(lhs, combine)?),
+                    Box::new(Self::balanced(rhs, combine)?),
+                )))
+            }
+        }
+    }
+}
+
+impl AssertionWrapper {
+    fn and_operands(&self) -> Option<(&Assertion, &Assertion)> {
+        match self {
+            AssertionWrapper::And {
+                lhs,
+                rhs,
+                flat: true,
+            } => Some((lhs, rhs)),
+            _ => None,
+        }
+    }
+    fn or_operands(&self) -> Option<(&Assertion, &Assertion)> {
+        match self {
+            AssertionWrapper::Or {
+                lhs,
+                rhs,
+                flat: true,
+            } => Some((lhs, rhs)),
+            _ => None,
+        }
+    }
+}
+
+impl std::ops::Not for Assertion {
+    type Output = Self;
+    fn not(self) -> Self::Output {
+        !&self
+    }
+}
+impl std::ops::Not for &Assertion {
+    type Output = Assertion;
+    fn not(self) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::Not {
+            assertion: Box::new(self.clone()),
+        })
+    }
+}
+
+impl BitAnd for Assertion {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::And {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+            flat: false,
+        })
+    }
+}
+impl BitAnd for &Assertion {
+    type Output = Assertion;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::And {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+            flat: false,
+        })
+    }
+}
+impl BitAnd<&Assertion> for Assertion {
+    type Output = Assertion;
+    fn bitand(self, rhs: &Self) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::And {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+            flat: false,
+        })
+    }
+}
+impl BitAnd<Assertion> for &Assertion {
+    type Output = Assertion;
+    fn bitand(self, rhs: Assertion) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::And {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+            flat: false,
+        })
+    }
+}
+impl BitOr for Assertion {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::Or {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+            flat: false,
+        })
+    }
+}
+impl BitOr for &Assertion {
+    type Output = Assertion;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::Or {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+            flat: false,
+        })
+    }
+}
+impl BitOr<&Assertion> for Assertion {
+    type Output = Self;
+    fn bitor(self, rhs: &Assertion) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::Or {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+            flat: false,
+        })
+    }
+}
+impl BitOr<Assertion> for &Assertion {
+    type Output = Assertion;
+    fn bitor(self, rhs: Assertion) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::Or {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+            flat: false,
+        })
+    }
+}
+
+impl BitXor for Assertion {
+    type Output = Self;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::Xor {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+        })
+    }
+}
+impl BitXor for &Assertion {
+    type Output = Assertion;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::Xor {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+        })
+    }
+}
+impl BitXor<&Assertion> for Assertion {
+    type Output = Self;
+    fn bitxor(self, rhs: &Assertion) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::Xor {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+        })
+    }
+}
+impl BitXor<Assertion> for &Assertion {
+    type Output = Assertion;
+    fn bitxor(self, rhs: Assertion) -> Self::Output {
+        Assertion::rooted(AssertionWrapper::Xor {
+            lhs: Box::new(self.clone()),
+            rhs: Box::new(rhs.clone()),
+        })
+    }
+}
+
+impl From<&Assertion> for bool {
+    fn from(value: &Assertion) -> Self {
+        use AssertionWrapper::*;
+        match &value.0 {
+            One {
+                assertion,
+                asserter,
+            } => {
+                if asserter.pass_all.load(SeqCst) {
+                    return true;
+                }
+                assertion.boolean.load(std::sync::atomic::Ordering::SeqCst)
+            }
+            And { lhs, rhs, .. } => bool::from(&**lhs) && bool::from(&**rhs),
+            Or { lhs, rhs, .. } => bool::from(&**lhs) || bool::from(&**rhs),
+            Xor { lhs, rhs } => bool::from(&**lhs) != bool::from(&**rhs),
+            Implies { lhs, rhs } => !bool::from(&**lhs) || bool::from(&**rhs),
+            Not { assertion } => !bool::from(&**assertion),
+            Labeled { inner, .. } => bool::from(&**inner),
+        }
+    }
+}
+impl From<Assertion> for bool {
+    fn from(value: Assertion) -> Self {
+        bool::from(&value)
+    }
+}
+
+/// Returns the named capture groups of `regex` which participated in matching `message`.
+#[cfg(feature = "regex")]
+fn named_captures(regex: &Regex, message: &str) -> HashMap<String, String> {
+    let Some(captures) = regex.captures(message) else {
+        return HashMap::new();
+    };
+    regex
+        .capture_names()
+        .flatten()
+        .filter_map(|name| Some((name.to_string(), captures.name(name)?.as_str().to_string())))
+        .collect()
+}
+
+/// Returns the capture groups of `regex` matching `message` by index, `None` for groups
+/// which did not participate.
+#[cfg(feature = "regex")]
+fn groups(regex: &Regex, message: &str) -> Vec<Option<String>> {
+    let Some(captures) = regex.captures(message) else {
+        return Vec::new();
+    };
+    captures
+        .iter()
+        .map(|group| group.map(|group| group.as_str().to_string()))
+        .collect()
+}
+
+/// A regex assertion which records the capture groups of the message it matched.
+///
+/// See [`Layer::regex_captures`].
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct CaptureAssertion(Assertion);
+
+#[cfg(feature = "regex")]
+impl CaptureAssertion {
+    /// Returns the text captured by the `n`th group in the matched message, where group
+    /// 0 is the whole match.
+    ///
+    /// Returns `None` when the assertion has not matched or the group did not participate
+    /// in the match.
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn group(&self, n: usize) -> Option<String> {
+        let AssertionWrapper::One { assertion, .. } = &(self.0).0 else {
+            unreachable!()
+        };
+        assertion.groups.lock().unwrap().get(n).cloned().flatten()
+    }
+    /// Asserts the `n`th group captured `expected`, see [`CaptureAssertion::group`].
+    ///
+    /// # Panics
+    ///
+    /// When the group did not capture `expected` or the inner mutex is poisoned.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert_group(n, y);` is ugly.
+    #[track_caller]
+    pub fn assert_group(&self, n: usize, expected: &str) -> &Self {
+        if !CHECKED || self.asserter().pass_all.load(SeqCst) {
+            return self;
+        }
+        let actual = self.group(n);
+        assert!(
+            actual.as_deref() == Some(expected),
+            "expected group {n} of {} to be {expected:?} but was {actual:?}",
+            self.ansi()
+        );
+        self
+    }
+}
+
+#[cfg(feature = "regex")]
+impl std::ops::Deref for CaptureAssertion {
+    type Target = Assertion;
+    fn deref(&self) -> &Assertion {
+        &self.0
+    }
+}
+
+/// A regex assertion which records the named capture groups of the message it matched.
+///
+/// See [`Layer::regex_named_captures`].
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct NamedCaptureAssertion(Assertion);
+
+#[cfg(feature = "regex")]
+impl NamedCaptureAssertion {
+    /// Returns the text captured by the group `name` in the matched message.
+    ///
+    /// Returns `None` when the assertion has not matched or the group did not participate
+    /// in the match.
+    ///
+    /// # Panics
+    ///
+    /// When the inner mutex is poisoned.
+    #[must_use]
+    pub fn capture(&self, name: &str) -> Option<String> {
+        let AssertionWrapper::One { assertion, .. } = &(self.0).0 else {
+            unreachable!()
+        };
+        assertion.captures.lock().unwrap().get(name).cloned()
+    }
+}
+
+#[cfg(feature = "regex")]
+impl std::ops::Deref for NamedCaptureAssertion {
+    type Target = Assertion;
+    fn deref(&self) -> &Assertion {
+        &self.0
+    }
+}
+
+/// Restores the previous default subscriber when dropped, see [`Layer::set_default`].
+#[derive(Debug)]
+pub struct LayerGuard {
+    asserter: Layer,
+    // Dropped after `Drop::drop` so the barrier is passed while the layer is still the default.
+    _guard: tracing::subscriber::DefaultGuard,
+}
+
+impl Drop for LayerGuard {
+    fn drop(&mut self) {
+        // Avoid a double panic when unwinding from a poisoned lock.
+        if !std::thread::panicking() {
+            self.asserter.barrier();
+        }
+    }
+}
+
+/// An assertion on the first event processed by a layer.
+///
+/// See [`Layer::first_event`].
+#[derive(Debug)]
+pub struct FirstEventAssertion {
+    asserter: Arc<InnerLayer>,
+}
+
+impl FirstEventAssertion {
+    /// Asserts the message of the first event equals `message`.
+    ///
+    /// # Panics
+    ///
+    /// When no event has been processed or the first event has a different message.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.message_eq(y);` is ugly.
+    #[track_caller]
+    pub fn message_eq(&self, message: &str) -> &Self {
+        if !CHECKED {
+            return self;
+        }
+        let first = self.first();
+        assert_eq!(first.message, message, "first event message");
+        self
+    }
+    /// Asserts the level of the first event equals `level`.
+    ///
+    /// # Panics
+    ///
+    /// When no event has been processed or the first event has a different level.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.level_eq(y);` is ugly.
+    #[track_caller]
+    pub fn level_eq(&self, level: Level) -> &Self {
+        if !CHECKED {
+            return self;
+        }
+        let first = self.first();
+        assert_eq!(first.level, level, "first event level");
+        self
+    }
+    #[track_caller]
+    fn first(&self) -> &FirstEvent {
+        self.asserter
+            .first_event
+            .get()
+            .expect("no event has been processed")
+    }
+}
+
+/// An assertion that a message was logged exactly once at a level.
+///
+/// See [`Layer::exactly_once_at_level`].
+#[derive(Debug)]
+pub struct ExactlyOnceAssertion {
+    /// Matches the message at any level.
+    any: Arc<InnerAssertion>,
+    /// Matches the message at `level`.
+    at_level: Arc<InnerAssertion>,
+    level: Level,
+    asserter: Arc<InnerLayer>,
+}
+
+impl ExactlyOnceAssertion {
+    /// Evaluates the assertion.
+    ///
+    /// # Panics
+    ///
+    /// When the message was never logged, was only logged at other levels or was logged
+    /// more than once at the level.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert();` is ugly.
+    #[track_caller]
+    pub fn assert(&self) -> &Self {
+        if !CHECKED || self.asserter.pass_all.load(SeqCst) {
+            return self;
+        }
+        let pattern = self.any.spec.assertion_type.to_string();
+        let level = self.level;
+        let at_level = self.at_level.occurrences.load(SeqCst);
+        assert!(
+            self.any.occurrences.load(SeqCst) != 0,
+            "expected {pattern:?} once at {level} but it was never logged"
+        );
+        assert!(
+            at_level != 0,
+            "expected {pattern:?} once at {level} but it was only logged at other levels"
+        );
+        assert!(
+            at_level == 1,
+            "expected {pattern:?} once at {level} but it was logged {at_level} times at {level}"
+        );
+        self
+    }
+}
+
+impl From<&ExactlyOnceAssertion> for bool {
+    fn from(value: &ExactlyOnceAssertion) -> Self {
+        value.asserter.pass_all.load(SeqCst) || value.at_level.occurrences.load(SeqCst) == 1
+    }
+}
+
+/// An assertion on the number of matching events, see [`Layer::count`] and
+/// [`Layer::events_in_span`].
+#[derive(Debug)]
+pub struct CountAssertion {
+    assertion: Arc<InnerAssertion>,
+    asserter: Arc<InnerLayer>,
+}
+
+impl CountAssertion {
+    /// Returns the number of matching events logged.
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.assertion.occurrences.load(SeqCst)
+    }
+    /// Asserts exactly `n` matching events were logged.
+    ///
+    /// # Panics
+    ///
+    /// When a different number of matching events were logged.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert_eq(y);` is ugly.
+    #[track_caller]
+    pub fn assert_eq(&self, n: usize) -> &Self {
+        self.check(|count| count == n, "", n)
+    }
+    /// Asserts at least `n` matching events were logged.
+    ///
+    /// # Panics
+    ///
+    /// When fewer than `n` matching events were logged.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert_at_least(y);` is ugly.
+    #[track_caller]
+    pub fn assert_at_least(&self, n: usize) -> &Self {
+        self.check(|count| count >= n, "at least ", n)
+    }
+    /// Asserts at most `n` matching events were logged.
+    ///
+    /// # Panics
+    ///
+    /// When more than `n` matching events were logged.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert_at_most(y);` is ugly.
+    #[track_caller]
+    pub fn assert_at_most(&self, n: usize) -> &Self {
+        self.check(|count| count <= n, "at most ", n)
+    }
+    #[track_caller]
+    fn check(&self, passes: impl Fn(usize) -> bool, bound: &str, n: usize) -> &Self {
+        if !CHECKED || self.asserter.pass_all.load(SeqCst) {
+            return self;
+        }
+        let count = self.count();
+        assert!(
+            passes(count),
+            "expected {:?} to be logged {bound}{n} times but it was logged {count} times",
+            self.assertion.spec.assertion_type.to_string()
+        );
+        self
+    }
+}
+
+/// Creates a [`SequenceAssertion`] from a script of the expected log output.
+///
+/// Each step is terminated by `;` and is one of:
+/// - `"message"`, an exact message, see [`AssertionSpec::exact`].
+/// - `regex "pattern"`, a message matching a regex, see [`AssertionSpec::regex`].
+///   Needs the `regex` feature, and panics if the regex is invalid.
+/// - `level LEVEL => step`, a step only matching events at a [`tracing::Level`],
+///   see [`AssertionSpec::with_level`].
+///
+/// ```
+/// use tracing_assertions::log_script;
+/// let asserter = tracing_assertions::Layer::default();
+/// let guard = asserter.set_default();
+/// let script = log_script! { asserter =>
+///     "starting";
+///     level ERROR => "shutting down";
+/// };
+/// tracing::info!("starting");
+/// tracing::info!("shutting down");
+/// tracing::error!("shutting down");
+/// script.assert();
+/// ```
+///
+/// With the `regex` feature:
+///
+#[cfg_attr(feature = "regex", doc = "```")]
+#[cfg_attr(not(feature = "regex"), doc = "```ignore")]
+/// use tracing_assertions::log_script;
+/// let asserter = tracing_assertions::Layer::default();
+/// let guard = asserter.set_default();
+/// let script = log_script! { asserter =>
+///     "starting";
+///     regex "listening on port \\d+";
+/// };
+/// tracing::info!("starting");
+/// tracing::info!("listening on port 80");
+/// script.assert();
+/// ```
+#[macro_export]
+macro_rules! log_script {
+    ($asserter:expr => $($steps:tt)*) => {
+        $asserter.sequence_of($crate::log_script!(@steps [] $($steps)*))
+    };
+    (@steps [$($specs:expr,)*]) => {
+        [$($specs,)*]
+    };
+    (@steps [$($specs:expr,)*] level $level:ident => $($rest:tt)*) => {
+        $crate::log_script!(@level $level [$($specs,)*] $($rest)*)
+    };
+    (@steps [$($specs:expr,)*] $($rest:tt)+) => {
+        $crate::log_script!(@step [$($specs,)*] $($rest)+)
+    };
+    (@level $level:ident [$($specs:expr,)*] regex $pattern:literal; $($rest:tt)*) => {
+        $crate::log_script!(@steps [
+            $($specs,)*
+            $crate::AssertionSpec::regex($pattern)
+                .unwrap()
+                .with_level(::tracing::Level::$level),
+        ] $($rest)*)
+    };
+    (@level $level:ident [$($specs:expr,)*] $message:literal; $($rest:tt)*) => {
+        $crate::log_script!(@steps [
+            $($specs,)*
+            $crate::AssertionSpec::exact($message).with_level(::tracing::Level::$level),
+        ] $($rest)*)
+    };
+    (@step [$($specs:expr,)*] regex $pattern:literal; $($rest:tt)*) => {
+        $crate::log_script!(@steps [
+            $($specs,)*
+            $crate::AssertionSpec::regex($pattern).unwrap(),
+        ] $($rest)*)
+    };
+    (@step [$($specs:expr,)*] $message:literal; $($rest:tt)*) => {
+        $crate::log_script!(@steps [
+            $($specs,)*
+            $crate::AssertionSpec::exact($message),
+        ] $($rest)*)
+    };
+}
+
+/// An assertion that messages were logged in a given order.
+#[derive(Debug)]
+pub struct SequenceAssertion {
+    sequence: Arc<InnerSequence>,
+    asserter: Arc<InnerLayer>,
+}
+
+impl SequenceAssertion {
+    /// Evaluates the assertion.
+    ///
+    /// # Panics
+    ///
+    /// When the sequence has not been fully observed, the message shows how far it got e.g.
+    /// `matched 2 of 4, stuck on "three"`.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.assert();` is ugly.
+    #[track_caller]
+    pub fn assert(&self) -> &Self {
+        if !CHECKED {
+            return self;
+        }
+        assert!(bool::from(self), "{}", self.failure());
+        self
+    }
+    /// Describes the progress of an incomplete sequence.
+    fn failure(&self) -> String {
+        let matched = self.sequence.matched.lock().unwrap().len();
+        let steps = &self.sequence.steps;
+        format!(
+            "matched {matched} of {}, stuck on {:?}: {}",
+            steps.len(),
+            steps[matched].assertion_type.to_string(),
+            self.ansi()
+        )
+    }
+    /// Asserts no two consecutive steps of the sequence matched more than `max` apart.
+    ///
+    /// Only the steps matched so far are considered.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// let asserter = tracing_assertions::Layer::default();
+    /// let registry = tracing_subscriber::Registry::default();
+    /// let subscriber = registry.with(asserter.clone());
+    /// let guard = tracing::subscriber::set_default(subscriber);
+    /// let handshake = asserter.sequence(["syn", "syn-ack", "ack"]);
+    /// tracing::info!("syn");
+    /// tracing::info!("syn-ack");
+    /// tracing::info!("ack");
+    /// handshake.assert().max_gap(Duration::from_secs(1));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When two consecutive steps matched more than `max` apart or the inner mutex is poisoned.
+    #[allow(clippy::must_use_candidate)] // `let _ = x.max_gap(y);` is ugly.
+    #[track_caller]
+    pub fn max_gap(&self, max: Duration) -> &Self {
+        if !CHECKED {
+            return self;
+        }
+        let matched = self.sequence.matched.lock().unwrap();
+        for (i, window) in matched.windows(2).enumerate() {
+            let gap = window[1] - window[0];
+            assert!(
+                gap <= max,
+                "{:?} -> {:?} took {gap:?} which exceeds {max:?}",
+                self.sequence.steps[i].assertion_type.to_string(),
+                self.sequence.steps[i + 1].assertion_type.to_string()
+            );
+        }
+        self
+    }
+
+    fn ansi(&self) -> String {
+        let pass_all = self.asserter.pass_all.load(SeqCst);
+        let matched = self.sequence.matched.lock().unwrap().len();
+        let steps = self
+            .sequence
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let str = format!("{:?}", step.assertion_type.to_string());
+                if pass_all || i < matched {
+                    self.asserter.paint(ansi_term::Colour::Green, str)
+                } else {
+                    self.asserter.paint(ansi_term::Colour::Red, str)
+                }
+            })
+            .collect::<Vec<_>>();
+        format!("[{}]", steps.join(" -> "))
+    }
+}
+
+impl From<&SequenceAssertion> for bool {
+    fn from(value: &SequenceAssertion) -> Self {
+        value.asserter.pass_all.load(SeqCst)
+            || value.sequence.matched.lock().unwrap().len() == value.sequence.steps.len()
+    }
+}
+
+/// The error returned when an assertion is false, see [`Assertion::try_assert`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionError(String);
+
+impl std::fmt::Display for AssertionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for AssertionError {}
+
+/// The error returned when an assertion is not satisfied in time, see [`Assertion::wait`].
+#[cfg(feature = "async")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutError(Duration);
+
+#[cfg(feature = "async")]
+impl std::fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "assertion not satisfied within {:?}", self.0)
+    }
+}
+
+#[cfg(feature = "async")]
+impl std::error::Error for TimeoutError {}
+
+/// Wakes a task at a deadline unless dropped first, see [`Assertion::wait_yielding`].
+#[cfg(feature = "async")]
+struct Timer(Arc<TimerState>);
+
+#[cfg(feature = "async")]
+struct TimerState {
+    /// The waker of the latest poll, `None` once cancelled.
+    waker: Mutex<Option<Waker>>,
+    cancelled: Condvar,
+}
+
+#[cfg(feature = "async")]
+impl Timer {
+    /// Starts a thread waking `waker` at `deadline`.
+    fn start(deadline: Instant, waker: &Waker) -> Self {
+        let state = Arc::new(TimerState {
+            waker: Mutex::new(Some(waker.clone())),
+            cancelled: Condvar::new(),
+        });
+        let shared = state.clone();
+        std::thread::spawn(move || {
+            let waker = shared.waker.lock().unwrap();
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let (waker, _) = shared
+                .cancelled
+                .wait_timeout_while(waker, timeout, |waker| waker.is_some())
+                .unwrap();
+            if let Some(waker) = &*waker {
+                waker.wake_by_ref();
+            }
+        });
+        Self(state)
+    }
+    /// Replaces the waker woken at the deadline.
+    fn update(&self, waker: &Waker) {
+        if let Some(current) = &mut *self.0.waker.lock().unwrap() {
+            current.clone_from(waker);
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for Timer {
+    fn drop(&mut self) {
+        *self.0.waker.lock().unwrap() = None;
+        self.0.cancelled.notify_one();
+    }
+}
+
+/// A report of the failing assertions across multiple layers.
+///
+/// ```
+/// let auth = tracing_assertions::Layer::default();
+/// let db = tracing_assertions::Layer::default();
+/// let login = auth.matches("login");
+/// let query = db.matches("query");
+/// auth.check_message("login");
+/// db.check_message("query");
+/// tracing_assertions::Report::new([&auth, &db]).assert_all();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Report(Vec<Layer>);
+
+impl Report {
+    /// Creates a report over the given layers.
+    pub fn new<'a>(layers: impl IntoIterator<Item = &'a Layer>) -> Self {
+        Self(layers.into_iter().cloned().collect())
+    }
+    /// Returns the failing assertions of each layer, paired with the index of the layer.
+    ///
+    /// This is [`Layer::failing`] of each layer, so a combined assertion with leaves
+    /// created by several layers is listed for each of them.
+    ///
+    /// # Panics
+    ///
+    /// When an internal mutex is poisoned.
+    #[must_use]
+    pub fn failing(&self) -> Vec<(usize, String)> {
+        self.0
+            .iter()
+            .enumerate()
+            .flat_map(|(i, layer)| layer.failing().into_iter().map(move |f| (i, f)))
+            .collect()
+    }
+    /// Asserts no layer has a failing assertion.
+    ///
+    /// # Panics
+    ///
+    /// When any layer has a failing assertion, listing each attributed to its layer.
+    #[track_caller]
+    pub fn assert_all(&self) {
+        if !CHECKED {
+            return;
+        }
+        let failing = self.failing();
+        assert!(
+            failing.is_empty(),
+            "{}",
+            failing
+                .iter()
+                .map(|(i, pattern)| format!("layer {i}: {pattern:?}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+}
+
+/// The inner sequence shared between sequence assertions and the assertion layer.
+#[derive(Debug)]
+struct InnerSequence {
+    steps: Vec<AssertionSpec>,
+    /// When each of the matched steps matched.
+    matched: Mutex<Vec<Instant>>,
+}
+
+/// The inner assertion shared between assertions and the assertion layer.
+///
+/// You should probably not use this directly.
+#[derive(Debug)]
+struct InnerAssertion {
+    /// Unique to the assertion, see [`Assertion::id`].
+    id: u64,
+    boolean: AtomicBool,
+    spec: AssertionSpec,
+    /// When the assertion last matched.
+    matched_at: Mutex<Option<Instant>>,
+    /// The ordinal of the event the assertion last matched, see [`Layer::checkpoint`].
+    matched_ordinal: Mutex<Option<u64>>,
+    /// The thread which logged the event the assertion last matched.
+    matched_thread: Mutex<Option<ThreadId>>,
+    /// The named capture groups of the message the assertion last matched.
+    captures: Mutex<HashMap<String, String>>,
+    /// The capture groups of the message the assertion last matched, by index.
+    groups: Mutex<Vec<Option<String>>>,
+    /// The number of events which satisfied the condition, see [`Layer::matches_nth`]
+    /// and [`Layer::exactly_once_at_level`].
+    occurrences: AtomicUsize,
+    /// Whether the assertion stays registered once matched, see [`Assertion::persist`].
+    persistent: AtomicBool,
+    /// Whether the assertion can no longer match, see [`Layer::expect_next`].
+    failed: AtomicBool,
+    /// The combined assertion this is a leaf of, see [`Layer::failing`].
+    root: Mutex<Option<Arc<Shape>>>,
+    /// Where the assertion was created.
+    #[cfg(feature = "creation-location")]
+    created_at: &'static Location<'static>,
+}
+
+/// The structure of a combined assertion, referring to its leaves weakly so it lives only
+/// as long as them, see [`Layer::failing`].
+#[derive(Debug)]
+enum Shape {
+    One(Weak<InnerAssertion>),
+    Not(Box<Shape>),
+    Labeled(String, Box<Shape>),
+    And(Box<Shape>, Box<Shape>),
+    Or(Box<Shape>, Box<Shape>),
+    Xor(Box<Shape>, Box<Shape>),
+    Implies(Box<Shape>, Box<Shape>),
+}
+
+impl Shape {
+    /// Returns whether the assertion is true, like [`bool::from`] for [`Assertion`].
+    fn holds(&self) -> bool {
+        match self {
+            Shape::One(assertion) => assertion
+                .upgrade()
+                .is_some_and(|assertion| assertion.boolean.load(SeqCst)),
+            Shape::Not(assertion) => !assertion.holds(),
+            Shape::Labeled(_, inner) => inner.holds(),
+            Shape::And(lhs, rhs) => lhs.holds() && rhs.holds(),
+            Shape::Or(lhs, rhs) => lhs.holds() || rhs.holds(),
+            Shape::Xor(lhs, rhs) => lhs.holds() != rhs.holds(),
+            Shape::Implies(lhs, rhs) => !lhs.holds() || rhs.holds(),
+        }
+    }
+}
+
+impl std::fmt::Display for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Shape::One(assertion) => match assertion.upgrade() {
+                Some(assertion) => write!(f, "{}", assertion.spec.assertion_type),
+                None => Ok(()),
+            },
+            Shape::Not(assertion) => write!(f, "!{assertion}"),
+            Shape::Labeled(label, inner) => write!(f, "[{label}] {inner}"),
+            Shape::And(lhs, rhs) => write!(f, "({lhs} && {rhs})"),
+            Shape::Or(lhs, rhs) => write!(f, "({lhs} || {rhs})"),
+            Shape::Xor(lhs, rhs) => write!(f, "({lhs} ^ {rhs})"),
+            Shape::Implies(lhs, rhs) => write!(f, "({lhs} => {rhs})"),
+        }
+    }
+}
+
+/// The id of the next assertion created.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+impl InnerAssertion {
+    #[track_caller]
+    fn new(spec: AssertionSpec) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, SeqCst),
+            boolean: AtomicBool::new(false),
+            spec,
+            matched_at: Mutex::new(None),
+            matched_ordinal: Mutex::new(None),
+            matched_thread: Mutex::new(None),
+            captures: Mutex::new(HashMap::new()),
+            groups: Mutex::new(Vec::new()),
+            occurrences: AtomicUsize::new(0),
+            failed: AtomicBool::new(false),
+            persistent: AtomicBool::new(false),
+            root: Mutex::new(None),
+            #[cfg(feature = "creation-location")]
+            created_at: Location::caller(),
+        }
+    }
+    /// Creates a new unmatched assertion with the same condition.
+    fn repeat(&self) -> Self {
+        Self {
+            persistent: AtomicBool::new(self.persistent.load(SeqCst)),
+            #[cfg(feature = "creation-location")]
+            created_at: self.created_at,
+            ..Self::new(self.spec.clone())
+        }
+    }
+    /// Clears what was recorded about the last match.
+    fn clear_match(&self) {
+        *self.matched_at.lock().unwrap() = None;
+        *self.matched_ordinal.lock().unwrap() = None;
+        *self.matched_thread.lock().unwrap() = None;
+        self.captures.lock().unwrap().clear();
+        self.groups.lock().unwrap().clear();
+        self.occurrences.store(0, SeqCst);
+    }
+}
+
+/// A description of the condition of a single assertion.
+///
+/// See [`Layer::template`], [`Layer::instantiate`] and [`Layer::sequence_of`].
+#[derive(Debug, Clone)]
+pub struct AssertionSpec {
+    assertion_type: AssertionType,
+    /// Only match events with this [`Metadata::name`].
+    event_name: Option<String>,
+    /// Only match events with this [`Metadata::level`].
+    level: Option<Level>,
+    /// Only match events at this [`Metadata::level`] or less verbose, see
+    /// [`Layer::matches_at_level`].
+    min_level: Option<Level>,
+    /// Only match events emitted on the thread with this name, see
+    /// [`Layer::matches_from_thread`].
+    thread: Option<String>,
+    /// Only match events with this [`Metadata::target`].
+    target: Option<String>,
+    /// Only consider the next event processed, see [`Layer::expect_next`].
+    next_only: bool,
+    /// Only match the nth matching event, see [`Layer::matches_nth`].
+    nth: Option<usize>,
+}
+
+impl AssertionSpec {
+    fn new(assertion_type: AssertionType) -> Self {
+        Self {
+            assertion_type,
+            event_name: None,
+            level: None,
+            min_level: None,
+            thread: None,
+            target: None,
+            next_only: false,
+            nth: None,
+        }
+    }
+    /// Creates a spec matching messages equal to `s`, as [`Layer::matches`].
+    pub fn exact(s: impl Into<String>) -> Self {
+        Self::new(AssertionType::Matches(s.into()))
+    }
+    /// Creates a spec matching messages matching a regex, as [`Layer::regex`].
+    ///
+    /// # Errors
+    ///
+    /// When the conversion to [`Regex`] fails.
+    #[cfg(feature = "regex")]
+    pub fn regex<T>(s: T) -> Result<Self, <Regex as TryFrom<T>>::Error>
+    where
+        Regex: TryFrom<T>,
+    {
+        Ok(Self::new(AssertionType::Regex(Regex::try_from(s)?)))
+    }
+    /// Only match events logged at `level`.
+    ///
+    /// Messages passed to [`Layer::check_message`] have no level, so never match.
+    #[must_use]
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.level = Some(level);
+        self
+    }
+    /// Only match events logged with the target `target`.
+    ///
+    /// Messages passed to [`Layer::check_message`] have no target, so never match.
+    #[must_use]
+    pub fn from_target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+    /// Returns the pattern when the spec only matches messages equal to it.
+    fn exact_pattern(&self) -> Option<&str> {
+        if self.event_name.is_some()
+            || self.level.is_some()
+            || self.min_level.is_some()
+            || self.thread.is_some()
+            || self.target.is_some()
+            || self.next_only
+            || self.nth.is_some()
+        {
+            return None;
+        }
+        match &self.assertion_type {
+            AssertionType::Matches(pattern) => Some(pattern),
+            AssertionType::MatchesStatic(pattern) => Some(pattern),
+            _ => None,
+        }
+    }
+    /// Returns [`AssertionSpec::exact_pattern`] as a key for [`Registered`], borrowing
+    /// static patterns.
+    fn exact_key(&self) -> Option<Cow<'static, str>> {
+        let pattern = self.exact_pattern()?;
+        Some(match &self.assertion_type {
+            AssertionType::MatchesStatic(pattern) => Cow::Borrowed(*pattern),
+            _ => Cow::Owned(pattern.to_owned()),
+        })
+    }
+    /// Returns whether an event satisfies the condition.
+    fn matches(&self, event: &EventRecord) -> bool {
+        if let Some(event_name) = &self.event_name {
+            if event
+                .metadata
+                .is_none_or(|metadata| metadata.name() != event_name)
+            {
+                return false;
+            }
+        }
+        if let Some(level) = &self.level {
+            if event
+                .metadata
+                .is_none_or(|metadata| metadata.level() != level)
+            {
+                return false;
+            }
+        }
+        if let Some(min_level) = &self.min_level {
+            if event
+                .metadata
+                .is_none_or(|metadata| metadata.level() > min_level)
+            {
+                return false;
+            }
+        }
+        if let Some(thread) = &self.thread {
+            // Events are processed as they are emitted, so this is the emitting thread.
+            if std::thread::current().name() != Some(thread.as_str()) {
+                return false;
+            }
+        }
+        if let Some(target) = &self.target {
+            if event
+                .metadata
+                .is_none_or(|metadata| metadata.target() != target)
+            {
+                return false;
+            }
+        }
+        matches_type(&self.assertion_type, event)
+    }
+}
+
+/// A field value recorded from an event.
+#[derive(Debug, Clone, PartialEq)]
+enum FieldValue {
+    /// Recorded with [`Visit::record_str`].
+    Str(String),
+    /// Recorded with [`Visit::record_debug`].
+    ///
+    /// Values recorded with the `%` and `?` sigils both end up here, as tracing
+    /// forwards [`tracing::field::display`] values to [`Visit::record_debug`].
+    Debug(String),
+    I64(i64),
+    U64(u64),
+    I128(i128),
+    U128(u128),
+    F64(f64),
+    Bool(bool),
+    /// Recorded with [`Visit::record_bytes`].
+    Bytes(Vec<u8>),
+}
+
+/// The type a field was recorded as, see [`Layer::assert_field_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FieldType {
+    /// Recorded with [`Visit::record_str`], e.g. `info!(user = "bob")`.
+    Str,
+    /// Recorded with [`Visit::record_debug`], e.g. `info!(user = ?user)` or `info!(user = %user)`.
+    Debug,
+    /// Recorded with [`Visit::record_i64`], e.g. `info!(count = 3)`.
+    I64,
+    /// Recorded with [`Visit::record_u64`], e.g. `info!(count = 3u64)`.
+    U64,
+    /// Recorded with [`Visit::record_i128`].
+    I128,
+    /// Recorded with [`Visit::record_u128`].
+    U128,
+    /// Recorded with [`Visit::record_f64`].
+    F64,
+    /// Recorded with [`Visit::record_bool`].
+    Bool,
+    /// Recorded with [`Visit::record_bytes`].
+    Bytes,
+}
+
+impl FieldValue {
+    /// Returns numeric values as `f64`, see [`Layer::field_lt`].
+    #[allow(clippy::cast_precision_loss)] // Thresholds don't need more than 52 bits.
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            FieldValue::I64(value) => Some(*value as f64),
+            FieldValue::U64(value) => Some(*value as f64),
+            FieldValue::I128(value) => Some(*value as f64),
+            FieldValue::U128(value) => Some(*value as f64),
+            FieldValue::F64(value) => Some(*value),
+            _ => None,
+        }
+    }
+    fn field_type(&self) -> FieldType {
+        match self {
+            FieldValue::Str(_) => FieldType::Str,
+            FieldValue::Debug(_) => FieldType::Debug,
+            FieldValue::I64(_) => FieldType::I64,
+            FieldValue::U64(_) => FieldType::U64,
+            FieldValue::I128(_) => FieldType::I128,
+            FieldValue::U128(_) => FieldType::U128,
+            FieldValue::F64(_) => FieldType::F64,
+            FieldValue::Bool(_) => FieldType::Bool,
+            FieldValue::Bytes(_) => FieldType::Bytes,
+        }
+    }
+    /// Renders the value as it would be by `Display`.
+    fn display(&self) -> String {
+        use FieldValue::*;
+        match self {
+            Str(value) | Debug(value) => value.clone(),
+            I64(value) => value.to_string(),
+            U64(value) => value.to_string(),
+            I128(value) => value.to_string(),
+            U128(value) => value.to_string(),
+            F64(value) => value.to_string(),
+            Bool(value) => value.to_string(),
+            Bytes(value) => hex_bytes(value),
+        }
+    }
+    /// Converts the value to JSON.
+    ///
+    /// 128 bit integers which do not fit in 64 bits are converted to strings.
+    #[cfg(feature = "serde")]
+    fn json(&self) -> serde_json::Value {
+        use FieldValue::*;
+        match self {
+            Str(value) | Debug(value) => serde_json::Value::from(value.as_str()),
+            I64(value) => serde_json::Value::from(*value),
+            U64(value) => serde_json::Value::from(*value),
+            I128(value) => i64::try_from(*value)
+                .map_or_else(|_| serde_json::Value::from(value.to_string()), Into::into),
+            U128(value) => u64::try_from(*value)
+                .map_or_else(|_| serde_json::Value::from(value.to_string()), Into::into),
+            F64(value) => serde_json::Value::from(*value),
+            Bool(value) => serde_json::Value::from(*value),
+            Bytes(value) => serde_json::Value::from(value.clone()),
+        }
+    }
+    /// Renders the value as it would be by `Debug`.
+    fn debug(&self) -> String {
+        use FieldValue::*;
+        match self {
+            Str(value) => format!("{value:?}"),
+            Debug(value) => value.clone(),
+            I64(value) => format!("{value:?}"),
+            U64(value) => format!("{value:?}"),
+            I128(value) => format!("{value:?}"),
+            U128(value) => format!("{value:?}"),
+            F64(value) => format!("{value:?}"),
+            Bool(value) => format!("{value:?}"),
+            Bytes(value) => hex_bytes(value),
+        }
+    }
+}
+
+struct EventVisitor<'a>(&'a mut Vec<(&'static str, FieldValue)>);
+impl Visit for EventVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .push((field.name(), FieldValue::Debug(format!("{value:?}"))));
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0
+            .push((field.name(), FieldValue::Str(value.to_string())));
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.push((field.name(), FieldValue::I64(value)));
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.push((field.name(), FieldValue::U64(value)));
+    }
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.0.push((field.name(), FieldValue::I128(value)));
+    }
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.0.push((field.name(), FieldValue::U128(value)));
+    }
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.push((field.name(), FieldValue::F64(value)));
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.push((field.name(), FieldValue::Bool(value)));
+    }
+    fn record_bytes(&mut self, field: &Field, value: &[u8]) {
+        self.0
+            .push((field.name(), FieldValue::Bytes(value.to_vec())));
+    }
+}
+
+/// A comparison of a numeric field, see [`Layer::field_lt`].
+#[derive(Debug, Clone, Copy)]
+enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+impl CmpOp {
+    #[allow(clippy::float_cmp)] // Equality is what was asked for.
+    fn compare(self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            CmpOp::Lt => lhs < rhs,
+            CmpOp::Le => lhs <= rhs,
+            CmpOp::Gt => lhs > rhs,
+            CmpOp::Ge => lhs >= rhs,
+            CmpOp::Eq => lhs == rhs,
+        }
+    }
+}
+
+impl std::fmt::Display for CmpOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+            CmpOp::Eq => "==",
+        };
+        write!(f, "{op}")
+    }
+}
+
+/// Renders bytes as tracing does by default, e.g. `[de ad]`.
+fn hex_bytes(bytes: &[u8]) -> String {
+    let hex = bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>();
+    format!("[{}]", hex.join(" "))
+}
+
+/// The parts of an event which assertions are checked against.
+#[derive(Debug, Default)]
+struct EventRecord {
+    message: String,
+    /// The fields of the event in declaration order.
+    fields: Vec<(&'static str, FieldValue)>,
+    /// The fields of the spans the event occurred within, innermost span first.
+    span_fields: Vec<(&'static str, FieldValue)>,
+    /// The name of the innermost span the event occurred within.
+    span: Option<&'static str>,
+    /// `None` for messages which did not come from a [`tracing`] event
+    /// (see [`Layer::check_message`]).
+    metadata: Option<&'static Metadata<'static>>,
+}
+
+impl EventRecord {
+    fn from_message(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            ..Self::default()
+        }
+    }
+    fn from_event(event: &Event<'_>, scope: MatchScope) -> Self {
+        let mut fields = Vec::new();
+        event.record(&mut EventVisitor(&mut fields) as &mut dyn Visit);
+        // Events bridged from `log` by `tracing-log` carry the metadata of the log
+        // record in `log.` prefixed fields after the message, these are not part of it.
+        let mut message_fields = fields.iter().filter(|(name, _)| !name.starts_with("log."));
+        let message = match scope {
+            // The message is usually the last field, but `message = ..` may be given
+            // anywhere, so only fall back to the last field when there isn't one.
+            MatchScope::Message => match fields.iter().find(|(name, _)| *name == "message") {
+                Some((_, value)) => value.display(),
+                None => message_fields
+                    .next_back()
+                    .map(|(_, value)| value.debug())
+                    .unwrap_or_default(),
+            },
+            MatchScope::AllFields => message_fields
+                .map(|(_, value)| value.debug())
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        Self {
+            message,
+            fields,
+            span_fields: Vec::new(),
+            span: None,
+            metadata: Some(event.metadata()),
+        }
+    }
+    /// Returns the value of the first field with the given name.
+    fn field(&self, name: &str) -> Option<&FieldValue> {
+        self.fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value)
+    }
+    /// Returns the fields other than `message`.
+    fn non_message_fields(&self) -> impl Iterator<Item = &(&'static str, FieldValue)> {
+        self.fields.iter().filter(|(field, _)| *field != "message")
+    }
+    /// Renders the event as `message key=value ...`, see [`Layer::anywhere`].
+    fn rendered(&self) -> String {
+        let message = match self.field("message") {
+            Some(message) => Some(message.display()),
+            None if self.fields.is_empty() => Some(self.message.clone()),
+            None => None,
+        };
+        let fields = self
+            .non_message_fields()
+            .filter(|(name, _)| !name.starts_with("log."))
+            .map(|(name, value)| format!("{name}={}", value.display()));
+        message
+            .into_iter()
+            .chain(fields)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Returns whether an event satisfies an assertion type.
+fn matches_type(assertion_type: &AssertionType, event: &EventRecord) -> bool {
+    let message = event.message.as_str();
+    match assertion_type {
+        AssertionType::Matches(expected) => *expected == message,
+        AssertionType::MatchesStatic(expected) => *expected == message,
+        AssertionType::Contains(expected) => message.contains(expected.as_str()),
+        AssertionType::StartsWith(prefix) => message.starts_with(prefix.as_str()),
+        AssertionType::EndsWith(suffix) => message.ends_with(suffix.as_str()),
+        AssertionType::OneOf(options) => options.contains(message),
+        AssertionType::MatchesUnicodeCi(expected) => fold_case(expected) == fold_case(message),
+        AssertionType::MatchesIgnoreCase(expected) => expected.eq_ignore_ascii_case(message),
+        #[cfg(feature = "regex")]
+        AssertionType::Regex(regex) => regex.is_match(message),
+        #[cfg(feature = "glob")]
+        AssertionType::Glob(glob) => glob.is_match(message),
+        #[cfg(feature = "regex")]
+        AssertionType::FieldRegex { name, regex } => event
+            .field(name)
+            .is_some_and(|field| regex.is_match(&field.display())),
+        AssertionType::FieldDisplay { name, value } => event
+            .field(name)
+            .is_some_and(|field| field.display() == *value),
+        AssertionType::FieldDebug { name, value } => event
+            .field(name)
+            .is_some_and(|field| field.debug() == *value),
+        AssertionType::FieldCmp { name, op, value } => event
+            .field(name)
+            .and_then(FieldValue::as_f64)
+            .is_some_and(|field| op.compare(field, *value)),
+        AssertionType::BareMessage(expected) => {
+            *expected == message && event.non_message_fields().next().is_none()
+        }
+        AssertionType::Anywhere(expected) => event.rendered().contains(expected.as_str()),
+        AssertionType::Predicate(predicate) => (predicate.0)(message),
+        AssertionType::Level(level) => event
+            .metadata
+            .is_some_and(|metadata| metadata.level() == level),
+        AssertionType::Target(target) => event
+            .metadata
+            .is_some_and(|metadata| metadata.target() == target),
+        AssertionType::Number { n, epsilon } => message
+            .trim()
+            .parse::<f64>()
+            .is_ok_and(|number| (number - n).abs() <= *epsilon),
+        AssertionType::Correlate {
+            span_fields,
+            event_fields,
+        } => {
+            span_fields.iter().all(|(name, value)| {
+                event
+                    .span_fields
+                    .iter()
+                    .any(|(field, field_value)| field == name && field_value.display() == *value)
+            }) && event_fields.iter().all(|(name, value)| {
+                event
+                    .field(name)
+                    .is_some_and(|field| field.display() == *value)
+            })
+        }
+        #[cfg(feature = "serde")]
+        AssertionType::Typed(typed) => (typed.matches)(event),
+        AssertionType::BytesContains { name, needle } => match event.field(name) {
+            Some(FieldValue::Bytes(bytes)) => {
+                needle.is_empty() || bytes.windows(needle.len()).any(|window| window == needle)
+            }
+            _ => false,
+        },
+        // Matched against spans, see `matches_span`.
+        AssertionType::SpanField { .. } | AssertionType::SpanEntered(_) => false,
+        AssertionType::InSpan(name) => event.span == Some(name.as_str()),
+        // With other fields present `message` may not be the last field, so the
+        // `message` field is compared directly.
+        AssertionType::HasAnyField(expected) => {
+            event
+                .field("message")
+                .is_some_and(|field| field.display() == *expected)
+                && event.non_message_fields().next().is_some()
+        }
+    }
+}
+
+/// Deserializes the fields of an event, or failing that its message, as JSON.
+#[cfg(feature = "serde")]
+fn deserialize_event<T: serde::de::DeserializeOwned>(event: &EventRecord) -> Option<T> {
+    let fields = event
+        .non_message_fields()
+        .map(|(name, value)| ((*name).to_string(), value.json()))
+        .collect::<serde_json::Map<_, _>>();
+    serde_json::from_value(serde_json::Value::Object(fields))
+        .ok()
+        .or_else(|| serde_json::from_str(&event.message).ok())
+}
+
+/// Returns whether a span satisfies an assertion type.
+fn matches_span(
+    assertion_type: &AssertionType,
+    name: &str,
+    fields: &[(&'static str, FieldValue)],
+) -> bool {
+    match assertion_type {
+        AssertionType::SpanField { span, field, value } => {
+            span == name
+                && fields
+                    .iter()
+                    .any(|(name, recorded)| name == field && recorded == value)
+        }
+        _ => false,
+    }
+}
+
+/// Returns whether entering a span satisfies an assertion type.
+fn matches_span_enter(assertion_type: &AssertionType, name: &str) -> bool {
+    matches!(assertion_type, AssertionType::SpanEntered(span) if span == name)
+}
+
+/// A compiled shell-style glob, see [`Layer::glob`].
+#[cfg(feature = "glob")]
+#[derive(Debug, Clone)]
+struct Glob {
+    pattern: String,
+    tokens: Vec<GlobToken>,
+}
+
+#[cfg(feature = "glob")]
+#[derive(Debug, Clone)]
+enum GlobToken {
+    Literal(char),
+    /// `?`
+    Any,
+    /// `*`
+    Star,
+    /// `[...]`, as inclusive ranges.
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+#[cfg(feature = "glob")]
+impl GlobToken {
+    /// Returns whether this token, which is not a star, matches `c`.
+    fn matches(&self, c: char) -> bool {
+        match self {
+            GlobToken::Literal(literal) => *literal == c,
+            GlobToken::Any => true,
+            GlobToken::Star => unreachable!(),
+            GlobToken::Class { negated, ranges } => {
+                ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c)) != *negated
+            }
+        }
+    }
+}
+
+#[cfg(feature = "glob")]
+impl Glob {
+    fn new(pattern: String) -> Result<Self, GlobError> {
+        let error = |reason| GlobError {
+            pattern: pattern.clone(),
+            reason,
+        };
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '*' => GlobToken::Star,
+                '?' => GlobToken::Any,
+                '\\' => GlobToken::Literal(chars.next().ok_or_else(|| error("trailing `\\`"))?),
+                '[' => {
+                    let negated = chars.next_if(|c| matches!(c, '!' | '^')).is_some();
+                    let mut ranges = Vec::new();
+                    // A `]` first in the set is literal.
+                    let mut first = true;
+                    loop {
+                        let lo = match chars.next() {
+                            Some(']') if !first => break,
+                            Some(c) => c,
+                            None => return Err(error("unclosed `[`")),
+                        };
+                        first = false;
+                        let hi = match chars.next_if_eq(&'-') {
+                            Some(_) => match chars.next() {
+                                // A trailing `-` is literal.
+                                Some(']') => {
+                                    ranges.push((lo, lo));
+                                    ranges.push(('-', '-'));
+                                    break;
+                                }
+                                Some(hi) => hi,
+                                None => return Err(error("unclosed `[`")),
+                            },
+                            None => lo,
+                        };
+                        ranges.push((lo, hi));
+                    }
+                    GlobToken::Class { negated, ranges }
+                }
+                c => GlobToken::Literal(c),
+            };
+            tokens.push(token);
+        }
+        Ok(Self { pattern, tokens })
+    }
+    /// Returns whether the whole of `message` matches the glob.
+    fn is_match(&self, message: &str) -> bool {
+        let message = message.chars().collect::<Vec<_>>();
+        let (mut t, mut m) = (0, 0);
+        // The token after the last star and the message position it was tried from,
+        // to backtrack to with the star consuming one more character.
+        let mut backtrack = None;
+        while m < message.len() {
+            match self.tokens.get(t) {
+                Some(GlobToken::Star) => {
+                    t += 1;
+                    backtrack = Some((t, m));
+                }
+                Some(token) if token.matches(message[m]) => {
+                    t += 1;
+                    m += 1;
+                }
+                _ => match backtrack {
+                    Some((star_t, star_m)) => {
+                        t = star_t;
+                        m = star_m + 1;
+                        backtrack = Some((star_t, m));
+                    }
+                    None => return false,
+                },
+            }
+        }
+        self.tokens[t..]
+            .iter()
+            .all(|token| matches!(token, GlobToken::Star))
+    }
+}
+
+/// The error returned for an invalid glob, see [`Layer::glob`].
+#[cfg(feature = "glob")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobError {
+    pattern: String,
+    reason: &'static str,
+}
+
+#[cfg(feature = "glob")]
+impl std::fmt::Display for GlobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid glob {:?}: {}", self.pattern, self.reason)
+    }
+}
+
+#[cfg(feature = "glob")]
+impl std::error::Error for GlobError {}
+
+/// Folds a string for Unicode-aware case-insensitive comparison.
+fn fold_case(s: &str) -> String {
+    let lowercase = s.to_lowercase();
+    #[cfg(feature = "unicode-normalization")]
+    let lowercase = unicode_normalization::UnicodeNormalization::nfc(lowercase.as_str()).collect();
+    lowercase
+}
+
+impl InnerLayer {
+    /// Paints `s` in `colour` when colour is enabled, see [`Layer::set_color`].
+    fn paint(&self, colour: ansi_term::Colour, s: String) -> String {
+        if self.color.0.load(SeqCst) {
+            colour.paint(s).to_string()
+        } else {
+            s
+        }
+    }
+    /// Registers an assertion to be updated by events.
+    ///
+    /// When bounded, this evicts the oldest registered assertions beyond capacity.
+    fn register(&self, assertion: Arc<InnerAssertion>) {
+        if let Some(target) = self.absorbed_into.lock().unwrap().clone() {
+            target.register(assertion);
+            return;
+        }
+        self.assertions
+            .lock()
+            .unwrap()
+            .push(assertion, self.capacity);
+    }
+    /// Makes a matched assertion unmatched, registering it again unless it is persistent.
+    ///
+    /// The `assertions` lock is held throughout so this can't interleave with processing
+    /// an event, which could otherwise leave an unmatched assertion with a match time.
+    fn rearm(self: &Arc<Self>, assertion: &Arc<InnerAssertion>) {
+        let layer = self.resolve();
+        let mut assertions = layer.assertions.lock().unwrap();
+        // A pending assertion counts its occurrences afresh too, see `Layer::matches_times`.
+        assertion.occurrences.store(0, SeqCst);
+        // A failed assertion was unregistered, even when persistent.
+        let failed = assertion.failed.swap(false, SeqCst);
+        if assertion.boolean.swap(false, SeqCst) || failed {
+            assertion.clear_match();
+            if failed || !assertion.persistent.load(SeqCst) {
+                assertions.push(assertion.clone(), layer.capacity);
+            }
+        }
+    }
+    /// Re-arms an assertion only if it matched, leaving a failed assertion failed.
+    fn rearm_matched(self: &Arc<Self>, assertion: &Arc<InnerAssertion>) {
+        let layer = self.resolve();
+        let mut assertions = layer.assertions.lock().unwrap();
+        if !assertion.failed.load(SeqCst) {
+            assertion.occurrences.store(0, SeqCst);
+        }
+        if assertion.boolean.swap(false, SeqCst) {
+            assertion.clear_match();
+            if !assertion.persistent.load(SeqCst) {
+                assertions.push(assertion.clone(), layer.capacity);
+            }
+        }
+    }
+    /// Returns the assertions which failed and were unregistered, forgetting those which
+    /// have since been dropped or reset.
+    fn failed(&self) -> Vec<Arc<InnerAssertion>> {
+        let mut failed = self.failed.lock().unwrap();
+        failed.retain(|assertion| {
+            assertion
+                .upgrade()
+                .is_some_and(|assertion| assertion.failed.load(SeqCst))
+        });
+        failed.iter().filter_map(Weak::upgrade).collect()
+    }
+    /// Unregisters an assertion, if it is registered.
+    fn unregister(&self, assertion: &Arc<InnerAssertion>) {
+        if let Some(target) = self.absorbed_into.lock().unwrap().clone() {
+            target.unregister(assertion);
+            return;
+        }
+        let mut assertions = self.assertions.lock().unwrap();
+        if let Some(i) = assertions
+            .iter()
+            .position(|registered| Arc::ptr_eq(registered, assertion))
+        {
+            assertions.remove(i);
+        }
+    }
+    /// Returns the layer this layer was absorbed into, or this layer if it was not absorbed.
+    fn resolve(self: &Arc<Self>) -> Arc<InnerLayer> {
+        let mut layer = self.clone();
+        loop {
+            let Some(target) = layer.absorbed_into.lock().unwrap().clone() else {
+                return layer;
+            };
+            layer = target;
+        }
+    }
+    /// Marks an event as being processed until the returned guard is dropped.
+    fn begin(self: &Arc<Self>) -> Processing {
+        let layer = self.resolve();
+        let mut in_flight = layer.in_flight.lock().unwrap();
+        let ticket = in_flight.started;
+        in_flight.started += 1;
+        in_flight.active.insert(ticket, std::thread::current().id());
+        drop(in_flight);
+        Processing { layer, ticket }
+    }
+    /// Records a combined assertion with leaves created by this layer.
+    fn track_root(&self, shape: &Arc<Shape>) {
+        let mut roots = self.roots.lock().unwrap();
+        // Drops the dropped assertions before growing, so this stays bounded by the live ones.
+        if roots.len() == roots.capacity() {
+            roots.retain(|root| root.strong_count() > 0);
+        }
+        roots.push(Arc::downgrade(shape));
+    }
+    /// Records a matched assertion to be reset by [`Layer::reset_on`] markers and
+    /// [`Layer::reset_all`].
+    fn track_matched(&self, assertion: &Arc<InnerAssertion>) {
+        if !self.reset_markers.lock().unwrap().is_empty() {
+            self.matched.lock().unwrap().push(Arc::downgrade(assertion));
+        }
+        let mut consumed = self.consumed.lock().unwrap();
+        // Drops the dropped assertions before growing, so this stays bounded by the live ones.
+        if consumed.len() == consumed.capacity() {
+            consumed.retain(|assertion| assertion.strong_count() > 0);
+        }
+        consumed.push(Arc::downgrade(assertion));
+    }
+    /// Updates the registered assertions against an event, removing those that match.
+    ///
+    /// The `assertions` lock is held while matching, so the assertions are consistent
+    /// whenever it is held.
+    fn process(&self, event: &EventRecord) {
+        let mut assertions = self.assertions.lock().unwrap();
+        let ordinal = self.events.fetch_add(1, SeqCst) + 1;
+        if self.reset_markers.lock().unwrap().contains(&event.message) {
+            for assertion in self.matched.lock().unwrap().drain(..) {
+                if let Some(assertion) = assertion.upgrade() {
+                    if assertion.boolean.swap(false, SeqCst) {
+                        assertion.clear_match();
+                        if !assertion.persistent.load(SeqCst) {
+                            assertions.push(assertion, self.capacity);
+                        }
+                    }
+                }
+            }
+        }
+        let mut matched = Vec::new();
+        let mut i = 0;
+        // Registered assertions are unaffected by events which can't match them.
+        if !assertions.may_match(&event.message) {
+            i = assertions.len();
+        }
+        while i < assertions.len() {
+            let mut result = assertions[i].spec.matches(event);
+            if result {
+                let occurrences = assertions[i].occurrences.fetch_add(1, SeqCst) + 1;
+                if let Some(n) = assertions[i].spec.nth {
+                    result = occurrences == n;
+                }
+            }
+            // Only persistent assertions are registered while true, and they stay true.
+            let was_true = assertions[i].boolean.fetch_or(result, SeqCst);
+            if result {
+                *assertions[i].matched_at.lock().unwrap() = Some(Instant::now());
+                *assertions[i].matched_ordinal.lock().unwrap() = Some(ordinal);
+                *assertions[i].matched_thread.lock().unwrap() = Some(std::thread::current().id());
+                #[cfg(feature = "regex")]
+                if let AssertionType::Regex(regex) = &assertions[i].spec.assertion_type {
+                    *assertions[i].captures.lock().unwrap() = named_captures(regex, &event.message);
+                    *assertions[i].groups.lock().unwrap() = groups(regex, &event.message);
+                }
+                matched.push(assertions[i].id);
+                if !was_true {
+                    self.track_matched(&assertions[i]);
+                }
+                if assertions[i].persistent.load(SeqCst) {
+                    i += 1;
+                } else {
+                    assertions.remove(i);
+                }
+            } else if assertions[i].spec.next_only {
+                assertions[i].failed.store(true, SeqCst);
+                self.failed
+                    .lock()
+                    .unwrap()
+                    .push(Arc::downgrade(&assertions[i]));
+                assertions.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        self.sequences.lock().unwrap().retain(|sequence| {
+            let mut matched = sequence.matched.lock().unwrap();
+            if sequence.steps[matched.len()].matches(event) {
+                matched.push(Instant::now());
+            }
+            matched.len() < sequence.steps.len()
+        });
+        drop(assertions);
+        self.notify_processed();
+        self.run_callbacks(&event.message, matched);
+        self.run_pattern_callbacks(&event.message);
+    }
+    /// Updates the registered span assertions against a span named `name`, removing those
+    /// that match.
+    fn process_span(&self, name: &str, satisfies: impl Fn(&AssertionType) -> bool) {
+        let mut assertions = self.assertions.lock().unwrap();
+        // Numbered like events, so span assertions can be related to checkpoints.
+        let ordinal = self.events.fetch_add(1, SeqCst) + 1;
+        let mut matched = Vec::new();
+        let mut i = 0;
+        while i < assertions.len() {
+            if satisfies(&assertions[i].spec.assertion_type) {
+                if !assertions[i].boolean.swap(true, SeqCst) {
+                    self.track_matched(&assertions[i]);
+                }
+                *assertions[i].matched_at.lock().unwrap() = Some(Instant::now());
+                *assertions[i].matched_ordinal.lock().unwrap() = Some(ordinal);
+                *assertions[i].matched_thread.lock().unwrap() = Some(std::thread::current().id());
+                matched.push(assertions[i].id);
+                if assertions[i].persistent.load(SeqCst) {
+                    i += 1;
+                } else {
+                    assertions.remove(i);
+                }
+            } else {
+                i += 1;
+            }
+        }
+        drop(assertions);
+        self.notify_processed();
+        self.run_callbacks(name, matched);
+    }
+    /// Wakes the threads, and tasks, waiting for an event to be processed.
+    fn notify_processed(&self) {
+        self.processed.notify_all();
+        #[cfg(feature = "async")]
+        for waker in self.wakers.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+    /// Runs the [`Layer::on_match`] callbacks for each of the `matched` assertions.
+    ///
+    /// Callbacks are run without holding any locks so they may use the layer.
+    fn run_callbacks(&self, message: &str, matched: Vec<u64>) {
+        if !matched.is_empty() {
+            let callbacks = self.on_match.lock().unwrap().clone();
+            for id in matched {
+                for callback in &callbacks.0 {
+                    callback(message, id);
+                }
+            }
+        }
+    }
+    /// Runs the [`Layer::on_pattern`] callbacks whose pattern equals `message`.
+    fn run_pattern_callbacks(&self, message: &str) {
+        let callbacks = self.on_pattern.lock().unwrap().clone();
+        for (pattern, callback) in &callbacks.0 {
+            if pattern == message {
+                (callback.lock().unwrap())(message);
+            }
+        }
+    }
+}
+
+/// The fields recorded on a span, stored in its extensions.
+struct SpanFields(Vec<(&'static str, FieldValue)>);
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> tracing_subscriber::layer::Layer<S> for Layer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let processing = self.0.begin();
+        let layer = &processing.layer;
+        let mut fields = Vec::new();
+        attrs.record(&mut EventVisitor(&mut fields) as &mut dyn Visit);
+        let name = attrs.metadata().name();
+        layer.process_span(name, |assertion| matches_span(assertion, name, &fields));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields));
+        }
+    }
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let processing = self.0.begin();
+        let layer = &processing.layer;
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut recorded = Vec::new();
+        values.record(&mut EventVisitor(&mut recorded) as &mut dyn Visit);
+        let mut extensions = span.extensions_mut();
+        let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() else {
+            return;
+        };
+        // Recording a field again replaces its value.
+        fields.retain(|(name, _)| !recorded.iter().any(|(field, _)| field == name));
+        fields.extend(recorded);
+        let fields = fields.clone();
+        drop(extensions);
+        layer.process_span(span.name(), |assertion| {
+            matches_span(assertion, span.name(), &fields)
+        });
+    }
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let processing = self.0.begin();
+        let layer = &processing.layer;
+        if let Some(span) = ctx.span(id) {
+            layer.process_span(span.name(), |assertion| {
+                matches_span_enter(assertion, span.name())
+            });
+        }
+    }
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let processing = self.0.begin();
+        let layer = &processing.layer;
+        let mut record = EventRecord::from_event(event, layer.scope);
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope {
+                record.span.get_or_insert(span.name());
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    record.span_fields.extend(fields.0.iter().cloned());
+                }
+            }
+        }
+        layer.first_event.get_or_init(|| FirstEvent {
+            message: record.message.clone(),
+            level: *event.metadata().level(),
+        });
+        if layer.recording {
+            layer
+                .recorded
+                .lock()
+                .unwrap()
+                .entry(*event.metadata().level())
+                .or_default()
+                .push(record.message.clone());
+            let target = event.metadata().target();
+            layer.transcript.lock().unwrap().push(RecordedEvent {
+                level: *event.metadata().level(),
+                target,
+                message: record.rendered(),
+            });
+            let mut targets = layer.targets.lock().unwrap();
+            if !targets.contains(target) {
+                targets.insert(target.to_string());
+            }
+            let mut field_types = layer.field_types.lock().unwrap();
+            for (name, value) in &record.fields {
+                field_types
+                    .entry(name)
+                    .or_default()
+                    .insert(value.field_type());
+            }
+        }
+        layer.process(&record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::info;
+
+    use super::*;
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_pass() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let condition = asserter.regex("01234.6789").unwrap();
+        info!("0123456789");
+        condition.assert();
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[cfg(feature = "regex")]
+    #[should_panic(expected = "\u{1b}[31m\"01234.789\"\u{1b}[0m")]
+    #[test]
+    fn regex_fail() {
+        let asserter = Layer::default();
+        asserter.set_color(true);
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let condition = asserter.regex("01234.789").unwrap();
+        info!("0123456789");
+        condition.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn debug() {
+        #[allow(dead_code)]
+        #[derive(Debug)]
+        struct MyStruct {
+            x: i32,
+            y: i32,
+        }
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let value = MyStruct { x: 2, y: 3 };
+        let condition = asserter.debug(&value);
+        info!("{value:?}");
+        condition.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn check_message() {
+        let asserter = Layer::default();
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        asserter.check_message("one");
+        one.assert();
+        (!&two).assert();
+        asserter.check_message("two");
+        two.assert();
+    }
+
+    #[test]
+    fn pass_all() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        info!("stuff");
+        let condition = asserter.matches("missing");
+        asserter.disable();
+        info!("more stuff");
+        condition.assert();
+        asserter.enable();
+        (!condition).assert();
+
+        drop(guard);
+    }
+
+    // Failures and `Debug` include the creation location with the feature.
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[cfg(not(feature = "creation-location"))]
+    #[test]
+    #[should_panic(
+        expected = "((\u{1b}[32m\"one\"\u{1b}[0m && \u{1b}[31m\"two\"\u{1b}[0m) || (\u{1b}[31m\"three\"\u{1b}[0m && !\u{1b}[31m\"four\"\u{1b}[0m))"
+    )]
+    fn panics() {
+        let asserter = Layer::default();
+        asserter.set_color(true);
+        let registry = Registry::default();
+        let subscriber = registry.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let three = asserter.matches("three");
+        let four = asserter.matches("four");
+        let assertion = one & two | three & !four;
+        info!("one");
+        asserter.disable();
+        assertion.assert();
+        assert_eq!(assertion.ansi(),"((\u{1b}[32m\"one\"\u{1b}[0m && \u{1b}[32m\"two\"\u{1b}[0m) || (\u{1b}[32m\"three\"\u{1b}[0m && !\u{1b}[32m\"four\"\u{1b}[0m))");
+        asserter.enable();
+        assertion.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn and() {
+        let asserter = Layer::default();
+        let registry = Registry::default();
+        let subscriber = registry.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let a = &one & two.clone();
+        let b = one.clone() & &two;
+        let c = &one & &two;
+        let d = one & two;
+        info!("one");
+        info!("two");
+        a.assert();
+        b.assert();
+        c.assert();
+        d.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn or() {
+        let asserter = Layer::default();
+        let registry = Registry::default();
+        let subscriber = registry.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let a = &one | two.clone();
+        let b = one.clone() | &two;
+        let c = &one | &two;
+        let d = one | two;
+        info!("one");
+        a.assert();
+        b.assert();
+        c.assert();
+        d.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn xor() {
+        let asserter = Layer::default();
+        asserter.set_color(true);
+        let registry = Registry::default();
+        let subscriber = registry.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let a = &one ^ two.clone();
+        let b = one.clone() ^ &two;
+        let c = &one ^ &two;
+        let d = one ^ two;
+        (!&a).assert();
+        info!("one");
+        a.assert();
+        b.assert();
+        c.assert();
+        d.assert();
+        assert!(c.matched_at().is_some());
+        assert!(c
+            .ansi()
+            .starts_with("(\u{1b}[32m\"one\"\u{1b}[0m ^ \u{1b}[31m\"two\"\u{1b}[0m"));
+        info!("two");
+        (!&a).assert();
+        (!&d).assert();
+        assert!(c.matched_at().is_none());
+        c.reset();
+        info!("two");
+        c.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn matches() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let two = asserter.matches("two");
+        let three = asserter.matches("three");
+        let or = &two | &three;
+        let and = &two & &three;
+        let or2 = two.clone() | three.clone();
+        let and2 = two.clone() & three.clone();
+
+        // The assertion is false as message matching `two` has not been encountered.
+        (!&two).assert();
+
+        info!("one");
+
+        // Still false.
+        (!&two).assert();
+        (!&or).assert();
+        (!&or2).assert();
+
+        info!("two");
+
+        // The assertion is true as a message matching `two` has been encountered.
+        two.assert();
+        or.assert();
+        or2.assert();
+        (!&and).assert();
+        (!&and2).assert();
+
+        info!("three");
+
+        // Still true.
+        two.assert();
+        and.assert();
+        and2.assert();
+
+        // If an assertion is created after the message, it will be false.
+        // Each assertion can only be fulfilled based on messages after its creation.
+        let two = asserter.matches("two");
+        (!&two).assert();
+        assert!(!bool::from(two));
+
+        drop(guard);
+    }
+
+    #[test]
+    fn repeat() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let or = &one | &two;
+        let and = &one & &two;
+        let not = !&one;
+
+        info!("one");
+        info!("two");
+
+        one.assert();
+        two.assert();
+        or.assert();
+        and.assert();
+        (!&not).assert();
+
+        let one2 = one.repeat();
+        let two2 = two.repeat();
+        let or2 = or.repeat();
+        let and2 = and.repeat();
+        let not2 = not.repeat();
+
+        (!&one2).assert();
+        (!&two2).assert();
+        (!&or2).assert();
+        (!&and2).assert();
+        (!(!&not2)).assert();
+
+        info!("one");
+        info!("two");
+
+        one2.assert();
+        two2.assert();
+        or2.assert();
+        and2.assert();
+        (!&not2).assert();
+
+        drop(guard);
+    }
+
+    #[test]
+    fn reset() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let or = &one | &two;
+        let and = &one & &two;
+        let not = !&one;
+
+        not.assert().reset();
+
+        info!("one");
+        info!("two");
+
+        one.assert().reset();
+        two.assert().reset();
+        or.assert().reset();
+        and.assert().reset();
+
+        (!&one).assert();
+        (!&two).assert();
+        (!&or).assert();
+        (!&and).assert();
+        (!&not).assert();
+
+        info!("one");
+        info!("two");
+
+        one.assert();
+        two.assert();
+        or.assert();
+        and.assert();
+        (!&not).assert();
+
+        drop(guard);
+    }
+
+    #[test]
+    fn rearm_matched() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let three = asserter.matches("three");
+        let and = &one & &two;
+        let or = &one | &three;
+
+        info!("one");
+
+        (!&and).assert();
+        or.assert();
+
+        and.rearm_matched();
+        or.rearm_matched();
+
+        // Only the matched `one` leaves were re-armed.
+        (!&and).assert();
+        (!&or).assert();
+
+        info!("two");
+
+        // The pending `two` leaf was left untouched so it still observes events.
+        (!&and).assert();
+        info!("one");
+        and.assert();
+        or.assert();
+
+        // Nothing matched, nothing is re-armed.
+        let four = asserter.matches("four");
+        four.rearm_matched();
+        (!&four).assert();
+        info!("four");
+        four.assert();
+
+        // Unlike `reset`, failed leaves stay failed.
+        let five = asserter.expect_next("five");
+        let six = asserter.expect_next("six");
+        info!("other");
+        assert_eq!(five.status(), AssertionStatus::Failed);
+        five.rearm_matched();
+        six.reset();
+        info!("six");
+        assert_eq!(five.status(), AssertionStatus::Failed);
+        six.assert();
+
+        drop(guard);
+    }
+
+    // Failures and `Debug` include the creation location with the feature.
+    #[cfg(not(feature = "creation-location"))]
+    #[test]
+    fn debug_state() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let assertion = &one & !&two;
+        info!("one");
+
+        assert_eq!(
+            format!("{one:?}"),
+            format!(
+                r#"Assertion(One {{ id: {}, pattern: "one", matched: true }})"#,
+                one.id()
+            )
+        );
+        let Assertion(AssertionWrapper::And { rhs, .. }) = &assertion else {
+            unreachable!()
+        };
+        assert_eq!(
+            format!("{assertion:?}"),
+            format!(
+                r#"Assertion(And {{ lhs: Assertion(One {{ id: {}, pattern: "one", matched: true }}), rhs: Assertion(Not {{ assertion: Assertion(One {{ id: {}, pattern: "two", matched: false }}) }}) }})"#,
+                assertion.id(),
+                rhs.id()
+            )
+        );
+
+        drop(guard);
+    }
+
+    #[test]
+    fn matched_at() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let and = &one & &two;
+        let or = &one | &two;
+        assert_eq!(one.matched_at(), None);
+
+        let before = Instant::now();
+        info!("one");
+        let between = Instant::now();
+        info!("two");
+        let after = Instant::now();
+
+        let one_at = one.matched_at().unwrap();
+        let two_at = two.matched_at().unwrap();
+        assert!(before <= one_at && one_at <= between);
+        assert!(between <= two_at && two_at <= after);
+        assert!(and.matched_at().unwrap() >= between);
+        assert!(or.matched_at().unwrap() <= between);
+        assert_eq!((!&one).matched_at(), None);
+
+        one.reset();
+        assert_eq!(one.matched_at(), None);
+
+        drop(guard);
+    }
+
+    #[test]
+    fn sequence() {
+        let asserter = Layer::default();
+        asserter.set_color(true);
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let sequence = asserter.sequence(["one", "two", "three"]);
+        info!("two");
+        info!("one");
+        info!("other");
+        info!("two");
+        assert!(!bool::from(&sequence));
+        assert_eq!(
+            sequence.ansi(),
+            "[\u{1b}[32m\"one\"\u{1b}[0m -> \u{1b}[32m\"two\"\u{1b}[0m -> \u{1b}[31m\"three\"\u{1b}[0m]"
+        );
+        info!("three");
+        sequence.assert().max_gap(Duration::from_secs(10));
+
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(expected = "\"one\" -> \"two\" took")]
+    fn sequence_max_gap() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let sequence = asserter.sequence(["one", "two"]);
+        info!("one");
+        std::thread::sleep(Duration::from_millis(20));
+        info!("two");
+        sequence.assert().max_gap(Duration::from_millis(10));
+
+        drop(guard);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn explain() {
+        let asserter = Layer::default();
+        let one = asserter.matches("one");
+        let regex = asserter.regex("o.e").unwrap();
+        let two = asserter.matches("two");
+        let and = &one & &two;
+
+        assert_eq!(asserter.explain("one"), ["one", "o.e"]);
+        assert_eq!(asserter.explain("two"), ["two"]);
+        assert!(asserter.explain("three").is_empty());
+
+        // Explaining does not update assertions.
+        (!&one).assert();
+        (!&regex).assert();
+        (!&two).assert();
+        (!&and).assert();
+    }
+
+    #[test]
+    fn matches_type_matches() {
+        let matches = |expected: &str, message: &str| {
+            matches_type(
+                &AssertionType::Matches(expected.into()),
+                &EventRecord::from_message(message),
+            )
+        };
+        assert!(matches("one", "one"));
+        assert!(!matches("one", "two"));
+        assert!(!matches("one", "one "));
+        assert!(!matches("one", "One"));
+        assert!(!matches("one", "on"));
+        assert!(matches("", ""));
+        assert!(!matches("", "one"));
+        assert!(matches("héllo wörld ✓", "héllo wörld ✓"));
+        assert!(!matches("héllo", "hello"));
+        assert!(matches("one\ntwo", "one\ntwo"));
+        assert!(!matches("one", "one\ntwo"));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn matches_type_regex() {
+        let matches = |regex: &str, message: &str| {
+            matches_type(
+                &AssertionType::Regex(Regex::new(regex).unwrap()),
+                &EventRecord::from_message(message),
+            )
+        };
+        assert!(matches("o.e", "one"));
+        assert!(matches("o.e", "stone"));
+        assert!(!matches("o.e", "two"));
+        assert!(matches("", ""));
+        assert!(matches("", "one"));
+        assert!(matches("w.rld", "wörld"));
+        assert!(matches("one\ntwo", "one\ntwo"));
+        assert!(!matches("e.t", "one\ntwo"));
+    }
+
+    #[test]
+    fn matches_unicode_ci() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let apples = asserter.matches_unicode_ci("ÄPFEL");
+        let sisyphus = asserter.matches_unicode_ci("ΣΊΣΥΦΟΣ");
+        let other = asserter.matches_unicode_ci("apfel");
+        info!("äpfel");
+        info!("σίσυφος");
+        apples.assert();
+        sisyphus.assert();
+        (!&other).assert();
+
+        drop(guard);
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn matches_unicode_ci_normalization() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let composed = asserter.matches_unicode_ci("CAFÉ");
+        info!("cafe\u{301}");
+        composed.assert();
+
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    fn assert_false() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let asserter = Layer::default();
+        asserter.set_color(true);
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let one = asserter.matches("one");
+        one.assert_false();
+        info!("one");
+
+        let message = |f: &dyn Fn()| {
+            *catch_unwind(AssertUnwindSafe(f))
+                .unwrap_err()
+                .downcast::<String>()
+                .unwrap()
+        };
+        let assert_false = message(&|| {
+            one.assert_false();
+        });
+        let not = message(&|| {
+            (!&one).assert();
+        });
+        let rendered = "\u{1b}[32m\"one\"\u{1b}[0m";
+        assert_eq!(
+            assert_false,
+            format!("expected absence but matched: {rendered}")
+        );
+        assert_eq!(not, format!("!{rendered}"));
+
+        drop(guard);
+    }
+
+    #[test]
+    fn failing() {
+        let asserter = Layer::default();
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        drop(asserter.matches("three"));
+        assert_eq!(asserter.failing(), ["one", "two"]);
+        asserter.check_message("one");
+        assert_eq!(asserter.failing(), ["two"]);
+        asserter.disable();
+        assert!(asserter.failing().is_empty());
+        asserter.enable();
+        one.reset();
+        assert_eq!(asserter.failing(), ["two", "one"]);
+        drop(two);
+        assert_eq!(asserter.failing(), ["one"]);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(expected = "layer 0: \"two\"\nlayer 1: \"four\"")]
+    fn report() {
+        let first = Layer::default();
+        let second = Layer::default();
+        let _one = first.matches("one");
+        let _two = first.matches("two");
+        let _three = second.matches("three");
+        let _four = second.matches("four");
+        first.check_message("one");
+        second.check_message("three");
+        let report = Report::new([&first, &second]);
+        assert_eq!(
+            report.failing(),
+            [(0, String::from("two")), (1, String::from("four"))]
+        );
+        report.assert_all();
+    }
+
+    #[test]
+    fn report_combined() {
+        let first = Layer::default();
+        let second = Layer::default();
+        let no_error = !first.matches("error");
+        let either = &first.matches("one") | &second.matches("two");
+        first.check_message("ok");
+        no_error.assert();
+        assert_eq!(first.failing(), ["(one || two)"]);
+        second.check_message("two");
+        either.assert();
+        assert!(first.failing().is_empty());
+        Report::new([&first, &second]).assert_all();
+        first.check_message("error");
+        assert_eq!(
+            Report::new([&first, &second]).failing(),
+            [(0, String::from("!error"))]
+        );
+        drop(no_error);
+        assert!(first.failing().is_empty());
+    }
+
+    #[test]
+    fn barrier() {
+        const WORKERS: usize = 8;
+        let asserter = Layer::default();
+        let dispatch = tracing::Dispatch::new(Registry::default().with(asserter.clone()));
+        let callbacks = Arc::new(AtomicUsize::new(0));
+        let counter = callbacks.clone();
+        let reentrant = asserter.clone();
+        asserter.on_match(move |_, _| {
+            // Waiting on the thread processing the event would otherwise deadlock.
+            reentrant.barrier();
+            std::thread::sleep(Duration::from_millis(20));
+            counter.fetch_add(1, SeqCst);
+        });
+        let done = (0..WORKERS)
+            .map(|i| asserter.matches(format!("worker {i} done")))
+            .collect::<Vec<_>>();
+        for i in 0..WORKERS {
+            let dispatch = dispatch.clone();
+            std::thread::spawn(move || {
+                tracing::dispatcher::with_default(&dispatch, || info!("worker {i} done"));
+            });
+        }
+        // Each event is matched before its callbacks have run.
+        for assertion in &done {
+            assert!(assertion.wait_blocking(Duration::from_secs(10)));
+        }
+        asserter.barrier();
+        assert_eq!(callbacks.load(SeqCst), WORKERS);
+    }
+
+    #[test]
+    fn with_event_name() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let named = asserter.with_event_name("named", "one");
+        let repeated = named.repeat();
+        let cloned = named.clone();
+        let located = asserter.with_event_name(format!("event {}:{}", file!(), line!() + 3), "one");
+
+        asserter.check_message("one");
+        info!("one");
+        (!&named).assert();
+        (!&repeated).assert();
+        (!&cloned).assert();
+        located.assert();
+
+        tracing::event!(name: "other", tracing::Level::INFO, "one");
+        (!&named).assert();
+        tracing::event!(name: "named", tracing::Level::INFO, "two");
+        (!&named).assert();
+        tracing::event!(name: "named", tracing::Level::INFO, "one");
+        named.assert();
+        repeated.assert();
+        cloned.assert();
+
+        drop(guard);
+    }
+
+    #[test]
+    fn template() {
+        let template = Layer::default();
+        template.matches("one");
+        template.with_event_name("named", "two");
+        let specs = template.template();
+        assert_eq!(specs.len(), 2);
+
+        for _ in 0..2 {
+            let asserter = Layer::default();
+            let base_subscriber = Registry::default();
+            let subscriber = base_subscriber.with(asserter.clone());
+            let guard = tracing::subscriber::set_default(subscriber);
+
+            let assertions = asserter.instantiate(&specs);
+            assert_eq!(assertions.len(), 2);
+            info!("one");
+            info!("two");
+            assertions[0].assert();
+            (!&assertions[1]).assert();
+            tracing::event!(name: "named", tracing::Level::INFO, "two");
+            assertions[1].assert();
+
+            drop(guard);
+        }
+
+        // Instantiating does not affect the template.
+        assert_eq!(template.failing().len(), 0);
+        assert_eq!(template.template().len(), 2);
+    }
+
+    #[test]
+    fn field_display_debug() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let str_display = asserter.field_display_eq("user", "bob");
+        let str_debug = asserter.field_debug_eq("user", "\"bob\"");
+        let str_wrong = asserter.field_debug_eq("user", "bob");
+        let display = asserter.field_display_eq("path", "a\\b");
+        let debug = asserter.field_debug_eq("ids", "[1, 2]");
+        let float_display = asserter.field_display_eq("ratio", "1");
+        let float_debug = asserter.field_debug_eq("ratio", "1.0");
+        let int = asserter.field_display_eq("count", "3");
+        let bool = asserter.field_display_eq("ok", "true");
+        let missing = asserter.field_display_eq("missing", "");
+
+        info!(
+            user = "bob",
+            path = %"a\\b",
+            ids = ?[1, 2],
+            ratio = 1.0,
+            count = 3,
+            ok = true,
+            "login"
+        );
+
+        str_display.assert();
+        str_debug.assert();
+        (!str_wrong).assert();
+        display.assert();
+        debug.assert();
+        float_display.assert();
+        float_debug.assert();
+        int.assert();
+        bool.assert();
+        (!missing).assert();
+
+        drop(guard);
+    }
+
+    #[test]
+    fn bounded() {
+        let asserter = Layer::builder().bounded(2).build();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let three = asserter.matches("three");
+        assert_eq!(asserter.failing(), ["two", "three"]);
+
+        info!("one");
+        info!("two");
+        info!("three");
+        (!&one).assert();
+        two.assert();
+        three.assert();
+
+        // Matched assertions free capacity, resetting registers them again.
+        let four = asserter.matches("four");
+        two.reset();
+        three.reset();
+        assert_eq!(asserter.failing(), ["two", "three"]);
+        info!("four");
+        (!&four).assert();
+
+        for i in 0..10_000 {
+            drop(asserter.matches(i.to_string()));
+        }
+        assert!(asserter.failing().is_empty());
+        assert_eq!(asserter.template().len(), 2);
+
+        drop(guard);
+    }
+
+    #[test]
+    fn assert_within() {
+        let asserter = Layer::default();
+        let dispatch = tracing::Dispatch::new(Registry::default().with(asserter.clone()));
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let and = &one & &two;
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            tracing::dispatcher::with_default(&dispatch, || {
+                info!("one");
+                std::thread::sleep(Duration::from_millis(20));
+                info!("two");
+            });
+        });
+        (!&and).assert();
+        and.assert_within(Duration::from_secs(10));
+        one.assert_within(Duration::ZERO);
+        thread.join().unwrap();
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(expected = "\u{1b}[31m\"one\"\u{1b}[0m")]
+    fn assert_within_timeout() {
+        let asserter = Layer::default();
+        asserter.set_color(true);
+        let one = asserter.matches("one");
+        asserter.check_message("two");
+        one.assert_within(Duration::from_millis(10));
+    }
+
+    #[test]
+    fn wait_blocking() {
+        let asserter = Layer::default();
+        let dispatch = tracing::Dispatch::new(Registry::default().with(asserter.clone()));
+        let done = asserter.matches("done");
+        let never = asserter.matches("never");
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            tracing::dispatcher::with_default(&dispatch, || info!("done"));
+        });
+        assert!(done.wait_blocking(Duration::from_secs(10)));
+        assert!(!never.wait_blocking(Duration::from_millis(10)));
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn install() {
+        let (asserter, guard) = Layer::install();
+        let one = asserter.matches("one");
+        info!("one");
+        drop(guard);
+        let two = asserter.matches("two");
+        info!("two");
+        one.assert();
+        (!&two).assert();
+    }
+
+    #[test]
+    fn set_default_barrier() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let dispatch = tracing::Dispatch::new(Registry::default().with(asserter.clone()));
+        let notified = Arc::new(AtomicBool::new(false));
+        let flag = notified.clone();
+        asserter.on_match(move |_, _| {
+            std::thread::sleep(Duration::from_millis(20));
+            flag.store(true, SeqCst);
+        });
+        let done = asserter.matches("done");
+        std::thread::spawn(move || tracing::dispatcher::with_default(&dispatch, || info!("done")));
+        assert!(done.wait_blocking(Duration::from_secs(10)));
+        drop(guard);
+        assert!(notified.load(SeqCst));
+    }
+
+    #[test]
+    fn wait_for_count() {
+        let asserter = Layer::default();
+        let done = asserter.matches("done").persist();
+        let once = asserter.matches("done");
+        let workers = (0..8)
+            .map(|_| {
+                let asserter = asserter.clone();
+                std::thread::spawn(move || asserter.check_message("done"))
+            })
+            .collect::<Vec<_>>();
+        assert!(done.wait_for_count(8, Duration::from_secs(10)));
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert!(!done.wait_for_count(9, Duration::from_millis(10)));
+        assert!(once.wait_for_count(1, Duration::ZERO));
+        assert!(!once.wait_for_count(2, Duration::ZERO));
+        done.reset();
+        assert!(done.labeled("workers").wait_for_count(0, Duration::ZERO));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a single assertion, not a combination")]
+    fn wait_for_count_combined() {
+        let asserter = Layer::default();
+        let both = asserter.matches("one") & asserter.matches("two");
+        let _ = both.wait_for_count(1, Duration::ZERO);
+    }
+
+    #[test]
+    fn match_scope() {
+        let asserter = Layer::builder().match_scope(MatchScope::AllFields).build();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let all = asserter.matches("login \"bob\" 3 [1, 2]");
+        let message = asserter.matches("login");
+        let bare = asserter.matches("bare");
+        let field = asserter.field_display_eq("user", "bob");
+        info!(user = "bob", count = 3, ids = ?[1, 2], "login");
+        info!("bare");
+        all.assert();
+        (!message).assert();
+        bare.assert();
+        field.assert();
+
+        drop(guard);
+    }
+
+    #[test]
+    fn on_match() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let matched = Arc::new(Mutex::new(Vec::new()));
+        let inner = matched.clone();
+        asserter.on_match(move |message, _| inner.lock().unwrap().push(message.to_string()));
+        let layer = asserter.clone();
+        // Callbacks may use the layer.
+        asserter.on_match(move |_, _| drop(layer.failing()));
+
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let both = &one & &two;
+        info!("zero");
+        info!("one");
+        info!("one");
+        info!("two");
+        both.assert();
+        assert_eq!(*matched.lock().unwrap(), ["one", "one", "two", "two"]);
+
+        drop(guard);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_named_captures() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let login = asserter
+            .regex_named_captures(r"user (?P<id>\d+) from (?P<ip>[\d.]+)")
+            .unwrap();
+        assert_eq!(login.capture("id"), None);
+        info!("user 7 from 10.0.0.1");
+        login.assert();
+        assert_eq!(login.capture("id").as_deref(), Some("7"));
+        assert_eq!(login.capture("ip").as_deref(), Some("10.0.0.1"));
+        assert_eq!(login.capture("port"), None);
+        login.reset();
+        assert_eq!(login.capture("id"), None);
+        drop(guard);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_captures() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let request = asserter
+            .regex_captures(r"(GET|POST) (/\S*)(?: in (\d+)ms)?")
+            .unwrap();
+        assert_eq!(request.group(0), None);
+        info!("GET /health");
+        request.assert();
+        assert_eq!(request.group(0).as_deref(), Some("GET /health"));
+        assert_eq!(request.group(1).as_deref(), Some("GET"));
+        request.assert_group(2, "/health");
+        assert_eq!(request.group(3), None);
+        assert_eq!(request.group(4), None);
+        request.reset();
+        assert_eq!(request.group(1), None);
+        info!("POST /login in 12ms");
+        request.assert_group(1, "POST").assert_group(3, "12");
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[cfg(feature = "regex")]
+    #[test]
+    #[should_panic(expected = "expected group 1 of")]
+    fn regex_captures_fail() {
+        let asserter = Layer::default();
+        let request = asserter.regex_captures(r"(GET|POST) /").unwrap();
+        asserter.check_message("GET /");
+        request.assert_group(1, "POST");
+    }
+
+    #[test]
+    fn first_event() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        asserter.check_message("not an event");
+        tracing::warn!("starting");
+        info!("running");
+        asserter
+            .first_event()
+            .message_eq("starting")
+            .level_eq(Level::WARN);
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[should_panic(expected = "no event has been processed")]
+    #[test]
+    fn first_event_none() {
+        let asserter = Layer::default();
+        asserter.first_event().message_eq("starting");
+    }
+
+    #[test]
+    fn bare_message() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let bare = asserter.is_bare_message("ready");
+        let structured = asserter.has_any_field("ready");
+        info!(port = 80, "ready");
+        (!&bare).assert();
+        structured.assert();
+        info!("ready");
+        bare.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn has_any_field() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let structured = asserter.has_any_field("ready");
+        asserter.check_message("ready");
+        info!("ready");
+        (!&structured).assert();
+        info!(port = 80, host = "a", "ready");
+        structured.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn matches_hex() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let packet = asserter.matches_hex(&[0x00, 0xAB, 0x0f]);
+        info!("payload 00AB0F");
+        (!&packet).assert();
+        info!("payload 00ab0f end");
+        packet.assert();
+        drop(guard);
+    }
+
+    #[test]
+    #[should_panic(expected = "no bytes to match")]
+    fn matches_hex_empty() {
+        let _ = Layer::default().matches_hex(&[]);
+    }
+
+    #[test]
+    fn expect_next() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let ack = asserter.expect_next("ack");
+        let later = asserter.matches("ack");
+        assert_eq!(asserter.failing(), ["ack", "ack"]);
+        assert_eq!(ack.status(), AssertionStatus::Pending);
+        info!("rst");
+        info!("ack");
+        (!&ack).assert();
+        later.assert();
+        assert_eq!(ack.status(), AssertionStatus::Failed);
+        assert_eq!(asserter.failing(), ["ack"]);
+        assert!(asserter.assertion_report().contains("failed"));
+        ack.reset();
+        assert_eq!(ack.status(), AssertionStatus::Pending);
+        info!("ack");
+        ack.assert();
+        assert!(asserter.failing().is_empty());
+        drop(guard);
+    }
+
+    #[test]
+    fn matches_one_of() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let status = asserter.matches_one_of(&["ok", "degraded", "down"]);
+        assert_eq!(asserter.failing(), [r#"one_of ["degraded", "down", "ok"]"#]);
+        info!("okay");
+        (!&status).assert();
+        info!("down");
+        status.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn checkpoint() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        assert_eq!(asserter.checkpoint(), 0);
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        info!("one");
+        let checkpoint = asserter.checkpoint();
+        assert_eq!(checkpoint, 1);
+        info!("two");
+        assert!(one.matched_before(checkpoint));
+        assert!(!one.matched_after(checkpoint));
+        assert!(two.matched_after(checkpoint));
+        assert!(!two.matched_before(checkpoint));
+        assert!((&one & &two).matched_after(checkpoint));
+        assert!((&one | &two).matched_before(checkpoint));
+        assert!(!(!&one).matched_before(checkpoint));
+        one.reset();
+        assert!(!one.matched_before(checkpoint));
+
+        // Spans are numbered like events.
+        let entered = asserter.span_entered("work");
+        let checkpoint = asserter.checkpoint();
+        tracing::info_span!("work").in_scope(|| info!("working"));
+        let worked = asserter.checkpoint();
+        assert!(entered.matched_after(checkpoint));
+        assert!(entered.matched_before(worked));
+        let done = asserter.matches("done");
+        info!("done");
+        entered.assert_before(&done);
+        drop(guard);
+    }
+
+    #[test]
+    fn correlate() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let delete = asserter.correlate(&[("table", "users"), ("db", "main")], &[("op", "delete")]);
+        let orders = asserter.correlate(&[("table", "orders")], &[("op", "delete")]);
+        info!(op = "delete", "outside");
+        (!&delete).assert();
+        let db = tracing::info_span!("db", db = "main");
+        let query = db.in_scope(|| tracing::info_span!("query", table = tracing::field::Empty));
+        query.in_scope(|| info!(op = "delete", "before record"));
+        (!&delete).assert();
+        query.record("table", "users");
+        query.in_scope(|| info!(op = "insert", "wrong op"));
+        (!&delete).assert();
+        query.in_scope(|| info!(op = "delete", "executing"));
+        delete.assert();
+        (!&orders).assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn set_default() {
+        let asserter = Layer::default();
+        let one = asserter.matches("one");
+        let guard = asserter.set_default();
+        let dispatch = tracing::dispatcher::get_default(Clone::clone);
+        let handle = std::thread::spawn(move || {
+            tracing::dispatcher::with_default(&dispatch, || info!("one"));
+        });
+        handle.join().unwrap();
+        drop(guard);
+        one.assert();
+        info!("two");
+        assert_eq!(asserter.checkpoint(), 1);
+    }
+
+    #[test]
+    fn recorded_at_level() {
+        let asserter = Layer::builder().recording().build();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        info!("one");
+        tracing::warn!("two");
+        info!("three");
+        asserter.check_message("four");
+        assert_eq!(asserter.recorded_at_level(Level::INFO), ["one", "three"]);
+        assert_eq!(asserter.recorded_at_level(Level::WARN), ["two"]);
+        assert!(asserter.recorded_at_level(Level::ERROR).is_empty());
+        drop(guard);
+
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        info!("one");
+        assert!(asserter.recorded_at_level(Level::INFO).is_empty());
+        drop(guard);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn field_regex() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let id = asserter.field_regex("request_id", "^[0-9a-f]{4}$").unwrap();
+        assert!(asserter.field_regex("request_id", "(").is_err());
+        info!("0bad");
+        info!(request_id = "0BAD", "handled");
+        info!(other_id = "0bad", "handled");
+        (!&id).assert();
+        info!(request_id = "0bad", "handled");
+        id.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn id() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let ids = Arc::new(Mutex::new(Vec::new()));
+        let inner = ids.clone();
+        asserter.on_match(move |_, id| inner.lock().unwrap().push(id));
+
+        let one = asserter.matches("one");
+        let other = asserter.matches("one");
+        assert_ne!(one.id(), other.id());
+        assert_ne!(one.id(), one.clone().id());
+        assert_ne!(one.id(), one.repeat().id());
+        assert_ne!(one.id(), (!&one).id());
+        info!("one");
+        let matched = ids.lock().unwrap().clone();
+        assert!(matched.contains(&one.id()));
+        assert!(matched.contains(&other.id()));
+
+        drop(guard);
+    }
+
+    #[test]
+    fn log() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        // Only fails if another logger was already set.
+        tracing_log::LogTracer::init().unwrap();
+        let one = asserter.matches("one");
+        let two = asserter.matches("two 2");
+        let user = asserter.field_display_eq("message", "user bob");
+        log::info!("one");
+        log::warn!(target: "other", "two {}", 2);
+        log::info!("user {}", "bob");
+        one.assert();
+        two.assert();
+        user.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn is_unsatisfied() {
+        let asserter = Layer::default();
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let and = &one & &two;
+        assert_eq!(asserter.failing().len(), 3);
+        assert!(and.is_unsatisfied());
+        // No assertions were cloned.
+        assert_eq!(asserter.failing().len(), 3);
+        asserter.check_message("one");
+        assert!(!one.is_unsatisfied());
+        assert!(and.is_unsatisfied());
+        asserter.check_message("two");
+        assert!(!and.is_unsatisfied());
+        asserter.disable();
+        assert!(!asserter.matches("three").is_unsatisfied());
+    }
+
+    #[test]
+    fn events_between() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let start = asserter.checkpoint();
+        info!("one");
+        info!("two");
+        asserter.check_message("three");
+        let end = asserter.checkpoint();
+        info!("four");
+        assert_eq!(asserter.events_between(start, end), 3);
+        assert_eq!(asserter.events_between(end, start), 0);
+        assert_eq!(asserter.events_between(end, u64::MAX), 1);
+        drop(guard);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn log_script() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let script = log_script! { asserter =>
+            "one";
+            regex "t[wo]+";
+            level WARN => "three";
+            level ERROR => regex "fo+ur";
+        };
+        let empty = log_script! { &asserter => };
+        empty.assert();
+        info!("one");
+        info!("two");
+        info!("three");
+        tracing::warn!("three");
+        tracing::warn!("four");
+        assert!(!bool::from(&script));
+        tracing::error!("four");
+        script.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn span_field() {
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let retries = asserter.span_field_i64("request", "retry_count", 3);
+        let unsigned = asserter.span_field_u64("request", "retry_count", 3);
+        let cached = asserter.span_field_bool("request", "cached", true);
+        let user = asserter.span_field_str("request", "user", "bob");
+        let other = asserter.span_field_i64("other", "retry_count", 3);
+        let message = asserter.matches("request");
+        let span = tracing::info_span!("request", retry_count = 0, cached = true, user = "bob",);
+        cached.assert();
+        user.assert();
+        (!&retries).assert();
+        span.record("retry_count", 3);
+        retries.assert();
+        (!&unsigned).assert();
+        (!&other).assert();
+        (!&message).assert();
+        // Creating the span and recording to it are numbered.
+        assert_eq!(asserter.checkpoint(), 2);
+        drop(guard);
+    }
+
+    #[test]
+    fn reset_on() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        asserter.reset_on("request_start");
+        let start = asserter.matches("request_start");
+        let handled = asserter.matches("handled");
+        let failed = asserter.matches("failed");
+        let ok = &handled & !&failed;
+
+        info!("request_start");
+        info!("handled");
+        start.assert();
+        ok.assert();
+
+        info!("request_start");
+        start.assert();
+        (!&handled).assert();
+        (!&ok).assert();
+        info!("failed");
+        info!("handled");
+        handled.assert();
+        (!&ok).assert();
+
+        info!("request_start");
+        (!&failed).assert();
+        info!("handled");
+        ok.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn reset_all() {
+        let asserter = Layer::builder().recording().build();
+        let guard = asserter.set_default();
+        let one = asserter.matches("one");
+        let two = asserter.matches("two").persist();
+        let entered = asserter.span_entered("request");
+        let either = &one | &two;
+
+        info!("one");
+        info!("two");
+        tracing::info_span!("request").in_scope(|| {});
+        one.assert();
+        two.assert();
+        entered.assert();
+        either.assert();
+
+        asserter.reset_all();
+        (!&one).assert();
+        (!&two).assert();
+        (!&entered).assert();
+        (!&either).assert();
+        assert!(asserter.recorded_messages().is_empty());
+
+        info!("two");
+        two.assert();
+        either.assert();
+        (!&one).assert();
+        assert_eq!(asserter.recorded_messages(), ["two"]);
+        drop(guard);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn field_matches_typed() {
+        #[derive(serde::Deserialize, PartialEq)]
+        struct Login {
+            user: String,
+            attempts: u32,
+        }
+        let asserter = Layer::default();
+        let base_subscriber = Registry::default();
+        let subscriber = base_subscriber.with(asserter.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        let login = asserter.field_matches_typed(Login {
+            user: String::from("bob"),
+            attempts: 2,
+        });
+        info!(user = "bob", attempts = 1, "login");
+        info!(user = 5, attempts = 2, "login");
+        info!(user = "bob", "login");
+        (!&login).assert();
+        info!(user = "bob", attempts = 2, extra = true, "login");
+        login.assert();
+
+        let json = asserter.field_matches_typed(Login {
+            user: String::from("alice"),
+            attempts: 3,
+        });
+        info!(r#"{{"user":"alice","attempts":3}}"#);
+        json.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn matches_nth() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let second = asserter.matches_nth("retrying", 2);
+        let first = asserter.matches_nth("retrying", 1);
+        let fifth = asserter.matches_nth("retrying", 5);
+        info!("retrying");
+        first.assert();
+        (!&second).assert();
+        info!("other");
+        info!("retrying");
+        second.assert();
+        info!("retrying");
+        (!&fifth).assert();
+        second.reset();
+        info!("retrying");
+        (!&second).assert();
+        info!("retrying");
+        second.assert();
+        drop(guard);
+    }
+
+    #[should_panic(expected = "occurrences are counted from 1")]
+    #[test]
+    fn matches_nth_zero() {
+        let asserter = Layer::default();
+        let _ = asserter.matches_nth("retrying", 0);
+    }
+
+    #[test]
+    fn exact_fast_path() {
+        let asserter = Layer::builder().bounded(3).build();
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        asserter.check_message("zero");
+        (!&one).assert();
+        asserter.check_message("one");
+        one.assert();
+        // A matched clone stays matched.
+        let and = &one & &two;
+        asserter.check_message("zero");
+        and.assert_false();
+        asserter.check_message("two");
+        and.assert();
+        // Evicted exact assertions are no longer indexed.
+        let _three = asserter.matches("three");
+        let _four = asserter.matches("four");
+        let _five = asserter.matches("five");
+        let six = asserter.matches_nth("six", 1);
+        asserter.check_message("six");
+        six.assert();
+        assert_eq!(asserter.failing(), ["four", "five"]);
+    }
+
+    #[test]
+    fn on_pattern() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let inner = seen.clone();
+        let mut count = 0;
+        asserter.on_pattern("retrying", move |message| {
+            count += 1;
+            inner.lock().unwrap().push(format!("{message} {count}"));
+        });
+        let layer = asserter.clone();
+        // Callbacks may use the layer.
+        asserter.on_pattern("retrying", move |_| layer.check_message("nested"));
+        let nested = asserter.matches("nested");
+        info!("retrying");
+        info!("connected");
+        asserter.check_message("retrying");
+        nested.assert();
+        assert_eq!(*seen.lock().unwrap(), ["retrying 1", "retrying 2"]);
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    fn assert_absent_for() {
+        let asserter = Layer::default();
+        let one = asserter.matches("one");
+        let start = Instant::now();
+        one.assert_absent_for(Duration::from_millis(20));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[should_panic(expected = "expected absence for 5s but matched")]
+    #[test]
+    fn assert_absent_for_fail() {
+        let asserter = Layer::default();
+        let dispatch = tracing::Dispatch::new(Registry::default().with(asserter.clone()));
+        let error = asserter.matches("error");
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            tracing::dispatcher::with_default(&dispatch, || info!("error"));
+        });
+        let start = Instant::now();
+        let result = std::panic::catch_unwind(|| {
+            error.assert_absent_for(Duration::from_secs(5));
+        });
+        // Fails as soon as the assertion matches.
+        assert!(start.elapsed() < Duration::from_secs(5));
+        std::panic::resume_unwind(result.unwrap_err());
+    }
+
+    #[test]
+    fn absorb() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let helper = Layer::default();
+        let one = helper.matches("one");
+        let sequence = helper.sequence(["one", "two"]);
+        asserter.absorb(&helper);
+        asserter.absorb(&helper);
+        helper.absorb(&asserter);
+        assert_eq!(asserter.failing(), ["one"]);
+        assert!(helper.failing().is_empty());
+        let two = helper.matches("two");
+        let later = helper.sequence(["two"]);
+        info!("one");
+        one.assert();
+        one.reset();
+        assert_eq!(asserter.failing(), ["two", "one"]);
+        info!("two");
+        two.assert();
+        sequence.assert();
+        later.assert();
+
+        let nested = Layer::default();
+        let three = nested.matches("three");
+        helper.absorb(&nested);
+        info!("three");
+        three.assert();
+        drop(guard);
+
+        // Events delivered to an absorbed layer are processed by the absorbing layer.
+        let main = Layer::default();
+        let helper = Layer::default();
+        let guard = helper.set_default();
+        let moved = helper.matches("moved");
+        main.absorb(&helper);
+        let created = helper.matches("created");
+        let entered = helper.span_entered("work");
+        info!("moved");
+        tracing::info_span!("work").in_scope(|| info!("created"));
+        moved.assert();
+        created.assert();
+        entered.assert();
+        assert_eq!(main.checkpoint(), 4);
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[should_panic(expected = "\u{1b}[31m\"one\"\u{1b}[0m")]
+    #[test]
+    fn checked() {
+        let asserter = Layer::default();
+        asserter.set_color(true);
+        asserter.matches("one").assert();
+    }
+
+    #[cfg(all(not(debug_assertions), feature = "debug-only"))]
+    #[test]
+    fn unchecked() {
+        let asserter = Layer::default();
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        one.assert();
+        assert!(one.try_assert().is_ok());
+        (!&two).assert_false();
+        one.assert_within(Duration::from_millis(1));
+        asserter.sequence(["one", "two"]).assert();
+        asserter.check_message("one");
+        assert!(bool::from(&one));
+        assert!(!bool::from(&two));
+        assert_eq!(asserter.failing().len(), 1);
+        Report::new([&asserter]).assert_all();
+    }
+
+    #[test]
+    fn failing_subtree() {
+        let asserter = Layer::default();
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let three = asserter.matches("three");
+        let four = asserter.matches("four");
+        let assertion = (&one & !&two) & ((&three | &four) & &one);
+        let pending = asserter.failing().len();
+        let failing = assertion.failing_subtree().unwrap();
+        // No assertions were registered.
+        assert_eq!(asserter.failing().len(), pending);
+        assert!(matches!(failing.0, AssertionWrapper::One { .. }));
+        assert!(format!("{failing:?}").contains(r#"pattern: "one""#));
+
+        asserter.check_message("one");
+        failing.assert();
+        let failing = assertion.failing_subtree().unwrap();
+        assert!(matches!(failing.0, AssertionWrapper::Or { .. }));
+
+        asserter.check_message("four");
+        assert!(assertion.failing_subtree().is_none());
+        asserter.check_message("two");
+        let failing = assertion.failing_subtree().unwrap();
+        assert!(matches!(failing.0, AssertionWrapper::Not { .. }));
+    }
+
+    #[test]
+    fn bytes_field_contains() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let header = asserter.bytes_field_contains("packet", &[0xca, 0xfe]);
+        let empty = asserter.bytes_field_contains("packet", &[]);
+        let display = asserter.field_display_eq("packet", "[00 ca fe]");
+        let packet: &[u8] = &[0xca, 0xfe];
+        info!(packet = ?packet, "sent");
+        info!(other = packet, "sent");
+        (!&header).assert();
+        (!&empty).assert();
+        let packet: &[u8] = &[0x00, 0xca, 0xfe];
+        info!(packet, "sent");
+        header.assert();
+        empty.assert();
+        display.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn assert_and_reset() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let tick = asserter.matches("tick");
+        for i in 0..3 {
+            info!("tick");
+            tick.assert_and_reset();
+            (!&tick).assert();
+            info!("tock {i}");
+            (!&tick).assert();
+        }
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(expected = "\u{1b}[31m\"tick\"\u{1b}[0m")]
+    fn assert_and_reset_fail() {
+        let asserter = Layer::default();
+        asserter.set_color(true);
+        let guard = asserter.set_default();
+        let tick = asserter.matches("tick");
+        info!("tick");
+        tick.assert_and_reset();
+        tick.assert_and_reset();
+        drop(guard);
+    }
+
+    #[test]
+    fn persist() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let once = asserter.matches("one");
+        let always = asserter.matches("one").persist();
+        let other = asserter.matches("two").persist();
+        info!("one");
+        once.assert();
+        always.assert();
+        let (once_at, always_at) = (once.matched_at(), always.matched_at());
+        info!("two");
+        always.assert();
+        other.assert();
+        info!("one");
+        assert_eq!(once.matched_at(), once_at);
+        assert!(always.matched_at() > always_at);
+
+        once.reset();
+        always.reset();
+        (!&once).assert();
+        (!&always).assert();
+        info!("one");
+        once.assert();
+        always.assert();
+        assert_eq!(asserter.template().len(), 0);
+        drop(guard);
+    }
+
+    #[test]
+    fn anywhere() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let field = asserter.anywhere("user=bob");
+        let value = asserter.anywhere("bob");
+        let span = asserter.anywhere("login user");
+        let message = asserter.anywhere("ogi");
+        let exact = asserter.matches("bob");
+        info!(user = "bob", count = 3, "login");
+        field.assert();
+        value.assert();
+        span.assert();
+        message.assert();
+        (!&exact).assert();
+        let fields_only = asserter.anywhere("count=3");
+        info!(count = 3);
+        fields_only.assert();
+        let checked = asserter.anywhere("eck");
+        asserter.check_message("checked");
+        checked.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn targets_seen() {
+        let asserter = Layer::builder().recording().build();
+        let guard = asserter.set_default();
+        assert!(asserter.targets_seen().is_empty());
+        info!(target: "auth", "login");
+        tracing::warn!(target: "db", "slow query");
+        info!(target: "auth", "logout");
+        info!("default");
+        asserter.check_message("checked");
+        assert_eq!(
+            asserter.targets_seen(),
+            HashSet::from([
+                String::from("auth"),
+                String::from("db"),
+                String::from(module_path!())
+            ])
+        );
+        drop(guard);
+
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        info!(target: "auth", "login");
+        assert!(asserter.targets_seen().is_empty());
+        drop(guard);
+    }
+
+    #[test]
+    fn matches_at_level() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let warn = asserter.matches_at_level("disk full", Level::WARN);
+        let error = asserter.matches_at_level("disk full", Level::ERROR);
+        tracing::debug!("disk full");
+        info!("disk full");
+        asserter.check_message("disk full");
+        (!&warn).assert();
+        tracing::warn!("disk full");
+        warn.assert();
+        (!&error).assert();
+        tracing::error!("disk full");
+        error.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn matches_from_thread() {
+        let asserter = Layer::default();
+        let dispatch = tracing::Dispatch::new(Registry::default().with(asserter.clone()));
+        let worker = asserter.matches_from_thread("done", "worker");
+        let other = asserter.matches_from_thread("done", "other");
+        let spawn = |name: Option<&str>| {
+            let dispatch = dispatch.clone();
+            let mut builder = std::thread::Builder::new();
+            if let Some(name) = name {
+                builder = builder.name(name.to_string());
+            }
+            builder
+                .spawn(move || tracing::dispatcher::with_default(&dispatch, || info!("done")))
+                .unwrap()
+                .join()
+                .unwrap();
+        };
+        spawn(None);
+        spawn(Some("main"));
+        (!&worker).assert();
+        spawn(Some("worker"));
+        worker.assert();
+        (!&other).assert();
+    }
+
+    #[test]
+    fn exactly_once_at_level() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let started = asserter.exactly_once_at_level(Level::INFO, "started");
+        assert!(!bool::from(&started));
+        tracing::debug!("started");
+        assert!(!bool::from(&started));
+        info!("started");
+        started.assert();
+        tracing::warn!("started");
+        started.assert();
+        info!("started");
+        assert!(!bool::from(&started));
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(expected = "expected \"started\" once at INFO but it was never logged")]
+    fn exactly_once_at_level_zero() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let started = asserter.exactly_once_at_level(Level::INFO, "started");
+        info!("starting");
+        started.assert();
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(
+        expected = "expected \"started\" once at INFO but it was only logged at other levels"
+    )]
+    fn exactly_once_at_level_wrong_level() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let started = asserter.exactly_once_at_level(Level::INFO, "started");
+        tracing::warn!("started");
+        started.assert();
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(
+        expected = "expected \"started\" once at INFO but it was logged 2 times at INFO"
+    )]
+    fn exactly_once_at_level_multiple() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let started = asserter.exactly_once_at_level(Level::INFO, "started");
+        info!("started");
+        info!("started");
+        started.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn assert_field_type() {
+        let asserter = Layer::builder().recording().build();
+        let guard = asserter.set_default();
+        info!(user_id = 7, "login");
+        info!(user_id = 8, name = "bob", "logout");
+        asserter.check_message("checked");
+        asserter.assert_field_type("user_id", FieldType::I64);
+        asserter.assert_field_type("name", FieldType::Str);
+        asserter.assert_field_type("message", FieldType::Debug);
+        asserter.assert_field_type("missing", FieldType::Bool);
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(
+        expected = "expected \"user_id\" to be recorded as I64 but it was also recorded as [Str, Debug]"
+    )]
+    fn assert_field_type_drift() {
+        let asserter = Layer::builder().recording().build();
+        let guard = asserter.set_default();
+        info!(user_id = 7, "login");
+        info!(user_id = "7", "login");
+        info!(user_id = ?7, "login");
+        asserter.assert_field_type("user_id", FieldType::I64);
+        drop(guard);
+    }
+
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(expected = "field types are only recorded by a layer built with")]
+    fn assert_field_type_not_recording() {
+        let asserter = Layer::default();
+        asserter.assert_field_type("user_id", FieldType::I64);
+    }
+
+    #[test]
+    fn muted() {
+        let asserter = Layer::default();
+        let one = asserter.matches("one");
+        assert!(asserter.muted(|| bool::from(&one)));
+        (!&one).assert();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            asserter.muted(|| panic!("setup failed"));
+        }));
+        assert!(result.is_err());
+        (!&one).assert();
+
+        asserter.disable();
+        asserter.muted(|| one.assert());
+        one.assert();
+        asserter.enable();
+        (!&one).assert();
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)] // The ratios are computed exactly.
+    fn level_ratio() {
+        let asserter = Layer::builder().recording().build();
+        let guard = asserter.set_default();
+        assert_eq!(asserter.level_ratio(Level::DEBUG, 10), 0.0);
+        tracing::error!("one");
+        tracing::trace!("two");
+        tracing::debug!("three");
+        info!("four");
+        asserter.check_message("five");
+        assert_eq!(asserter.level_ratio(Level::DEBUG, 4), 0.5);
+        assert_eq!(asserter.level_ratio(Level::DEBUG, 100), 0.5);
+        assert_eq!(asserter.level_ratio(Level::DEBUG, 3), 2.0 / 3.0);
+        assert_eq!(asserter.level_ratio(Level::DEBUG, 1), 0.0);
+        assert_eq!(asserter.level_ratio(Level::TRACE, 4), 0.25);
+        assert_eq!(asserter.level_ratio(Level::ERROR, 4), 1.0);
+        assert_eq!(asserter.level_ratio(Level::DEBUG, 0), 0.0);
+        drop(guard);
+
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        tracing::debug!("one");
+        assert_eq!(asserter.level_ratio(Level::DEBUG, 1), 0.0);
+        drop(guard);
+    }
+
+    #[test]
+    fn chained_filters() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let denied = asserter
+            .matches("denied")
+            .at_level(Level::ERROR)
+            .from_target("auth")
+            .persist();
+        let id = denied.id();
+        let either = (asserter.matches("one") | asserter.matches("two")).from_target("db");
+        let plain = asserter.matches("denied");
+
+        tracing::error!(target: "db", "denied");
+        tracing::warn!(target: "auth", "denied");
+        plain.assert();
+        (!&denied).assert();
+        tracing::error!(target: "auth", "denied");
+        denied.assert();
+        assert_eq!(denied.id(), id);
+        tracing::error!(target: "auth", "denied");
+        denied.assert();
+
+        asserter.check_message("one");
+        info!("two");
+        (!&either).assert();
+        info!(target: "db", "two");
+        either.assert();
+
+        let matched = asserter.matches("three");
+        info!("three");
+        let refined = matched.at_level(Level::WARN);
+        (!&refined).assert();
+        tracing::warn!("three");
+        refined.assert();
+        drop(guard);
+    }
+
+    #[cfg(feature = "creation-location")]
+    #[test]
+    fn creation_location() {
+        let asserter = Layer::default();
+        let line = line!() + 1;
+        let one = asserter.matches("one");
+        let repeated = one.repeat();
+        let filtered = asserter.matches("two").at_level(Level::INFO);
+        let [instantiated] = asserter
+            .instantiate(&[AssertionSpec::exact("three")])
+            .try_into()
+            .unwrap();
+        let location = |assertion: &Assertion| match &assertion.0 {
+            AssertionWrapper::One { assertion, .. } => assertion.created_at,
+            _ => unreachable!(),
+        };
+        assert_eq!(location(&one).file(), file!());
+        assert_eq!(location(&one).line(), line);
+        assert_eq!(location(&repeated), location(&one));
+        assert_eq!(location(&filtered).line(), line + 2);
+        assert_eq!(location(&instantiated).line(), line + 4);
+        assert!((!&one).ansi().ends_with(&format!(
+            "(created at {}:{line}:{})",
+            file!(),
+            location(&one).column()
+        )));
+        assert!(format!("{one:?}").contains(&format!("created_at: \"{}", file!())));
+        asserter.check_message("one");
+        assert!(!one.ansi().contains("created at"));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn wait_yielding() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let done = asserter.matches("done");
+            let never = asserter.matches("never");
+            tokio::spawn(async {
+                tokio::task::yield_now().await;
+                info!("done");
+            });
+            assert!(done.wait_yielding(Duration::from_secs(5)).await);
+            // Wakers are only registered while pending.
+            assert!(done.wait_yielding(Duration::from_secs(5)).await);
+            assert!(asserter.0.wakers.lock().unwrap().is_empty());
+            let start = Instant::now();
+            assert!(!never.wait_yielding(Duration::from_millis(50)).await);
+            assert!(start.elapsed() >= Duration::from_millis(50));
+        });
+        drop(guard);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn wait() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let done = asserter.matches("done");
+            let never = asserter.matches("never");
+            tokio::spawn(async {
+                tokio::task::yield_now().await;
+                info!("done");
+            });
+            done.wait(Duration::from_secs(5)).await.unwrap();
+            let error = never.wait(Duration::from_millis(10)).await.unwrap_err();
+            assert_eq!(error.to_string(), "assertion not satisfied within 10ms");
+        });
+        drop(guard);
+    }
+
+    #[test]
+    fn concurrent_stress() {
+        const THREADS: usize = 4;
+        const ITERATIONS: usize = 2_000;
+        let asserter = Layer::default();
+        let dispatch = tracing::Dispatch::new(Registry::default().with(asserter.clone()));
+        let assertions = (0..THREADS)
+            .map(|_| asserter.matches("one"))
+            .collect::<Vec<_>>();
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                let dispatch = dispatch.clone();
+                scope.spawn(move || {
+                    tracing::dispatcher::with_default(&dispatch, || {
+                        for i in 0..ITERATIONS {
+                            info!("one");
+                            info!("two {i}");
+                        }
+                    });
+                });
             }
-            Not { assertion } => assertion.reset(),
-            And { lhs, rhs } | Or { lhs, rhs } => {
-                lhs.reset();
-                rhs.reset();
+            // Processing and resetting hold the lock, so the state is consistent when holding it.
+            let observer = scope.spawn(|| {
+                for _ in 0..ITERATIONS {
+                    let _assertions = asserter.0.assertions.lock().unwrap();
+                    for assertion in &assertions {
+                        let AssertionWrapper::One { assertion, .. } = &assertion.0 else {
+                            unreachable!()
+                        };
+                        assert_eq!(
+                            assertion.boolean.load(SeqCst),
+                            assertion.matched_at.lock().unwrap().is_some()
+                        );
+                    }
+                }
+            });
+            for assertion in &assertions {
+                scope.spawn(move || {
+                    for _ in 0..ITERATIONS {
+                        assertion.reset();
+                        let repeated = assertion.repeat();
+                        let _ = bool::from(&(&repeated | assertion));
+                        assertion.rearm_matched();
+                    }
+                });
             }
+            observer.join().unwrap();
+        });
+        // Every assertion is registered or true, and only true assertions have matched.
+        for assertion in &assertions {
+            assert_eq!(
+                bool::from(assertion),
+                assertion.matched_at().is_some(),
+                "{assertion:?}"
+            );
+        }
+        tracing::dispatcher::with_default(&dispatch, || info!("one"));
+        for assertion in &assertions {
+            assertion.assert();
+        }
+        let registered = asserter.0.assertions.lock().unwrap();
+        for assertion in &assertions {
+            let AssertionWrapper::One { assertion, .. } = &assertion.0 else {
+                unreachable!()
+            };
+            assert!(!registered.iter().any(|r| Arc::ptr_eq(r, assertion)));
         }
     }
 
-    fn ansi(&self) -> String {
-        use AssertionWrapper::*;
-
-        match &self.0 {
-            One {
-                assertion,
-                asserter,
-            } => {
-                let is_true = if asserter.pass_all.load(SeqCst) {
-                    true
-                } else {
-                    assertion.boolean.load(std::sync::atomic::Ordering::SeqCst)
-                };
-                let str = format!("{:?}", assertion.assertion_type.to_string());
-                let out = if is_true {
-                    ansi_term::Colour::Green.paint(str)
-                } else {
-                    ansi_term::Colour::Red.paint(str)
-                };
-                out.to_string()
+    #[test]
+    fn level_sequence() {
+        let asserter = Layer::builder().recording().build();
+        let guard = asserter.set_default();
+        assert!(asserter.level_sequence().is_empty());
+        for attempt in 1..=3 {
+            if attempt == 1 {
+                info!("attempt {attempt}");
+            } else {
+                tracing::warn!("retry {attempt}");
             }
-            And { lhs, rhs } => format!("({} && {})", lhs.ansi(), rhs.ansi()),
-            Or { lhs, rhs } => format!("({} || {})", lhs.ansi(), rhs.ansi()),
-            Not { assertion } => format!("!{}", assertion.ansi()),
         }
-    }
-}
+        asserter.check_message("unrecorded");
+        tracing::error!("giving up");
+        assert_eq!(
+            asserter.level_sequence(),
+            [Level::INFO, Level::WARN, Level::WARN, Level::ERROR]
+        );
+        drop(guard);
 
-impl std::ops::Not for Assertion {
-    type Output = Self;
-    fn not(self) -> Self::Output {
-        !&self
-    }
-}
-impl std::ops::Not for &Assertion {
-    type Output = Assertion;
-    fn not(self) -> Self::Output {
-        Assertion(AssertionWrapper::Not {
-            assertion: Box::new(self.clone()),
-        })
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        info!("one");
+        assert!(asserter.level_sequence().is_empty());
+        drop(guard);
     }
-}
 
-impl BitAnd for Assertion {
-    type Output = Self;
-    fn bitand(self, rhs: Self) -> Self::Output {
-        Assertion(AssertionWrapper::And {
-            lhs: Box::new(self.clone()),
-            rhs: Box::new(rhs.clone()),
-        })
-    }
-}
-impl BitAnd for &Assertion {
-    type Output = Assertion;
-    fn bitand(self, rhs: Self) -> Self::Output {
-        Assertion(AssertionWrapper::And {
-            lhs: Box::new(self.clone()),
-            rhs: Box::new(rhs.clone()),
-        })
-    }
-}
-impl BitAnd<&Assertion> for Assertion {
-    type Output = Assertion;
-    fn bitand(self, rhs: &Self) -> Self::Output {
-        Assertion(AssertionWrapper::And {
-            lhs: Box::new(self.clone()),
-            rhs: Box::new(rhs.clone()),
-        })
-    }
-}
-impl BitAnd<Assertion> for &Assertion {
-    type Output = Assertion;
-    fn bitand(self, rhs: Assertion) -> Self::Output {
-        Assertion(AssertionWrapper::And {
-            lhs: Box::new(self.clone()),
-            rhs: Box::new(rhs.clone()),
-        })
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    fn soft_mode() {
+        let asserter = Layer::default();
+        asserter.soft_mode(true);
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let three = asserter.matches("three");
+        asserter.check_message("two");
+        let line = line!() + 1;
+        one.assert();
+        two.assert();
+        three.assert().assert();
+        let failures = asserter.0.failures.lock().unwrap().clone();
+        assert_eq!(
+            failures,
+            [
+                format!("{}:{line}:13: {}", file!(), one.ansi()),
+                format!("{}:{}:15: {}", file!(), line + 2, three.ansi()),
+                format!("{}:{}:24: {}", file!(), line + 2, three.ansi()),
+            ]
+        );
+        let flushed = std::panic::catch_unwind(|| asserter.flush_failures()).unwrap_err();
+        assert_eq!(
+            flushed.downcast_ref::<String>().unwrap(),
+            &format!("3 soft assertion failures:\n{}", failures.join("\n"))
+        );
+        asserter.flush_failures();
+
+        asserter.soft_mode(false);
+        assert!(std::panic::catch_unwind(|| one.assert()).is_err());
+        asserter.flush_failures();
     }
-}
-impl BitOr for Assertion {
-    type Output = Self;
-    fn bitor(self, rhs: Self) -> Self::Output {
-        Assertion(AssertionWrapper::Or {
-            lhs: Box::new(self.clone()),
-            rhs: Box::new(rhs.clone()),
-        })
+
+    #[test]
+    fn matches_number() {
+        let matches = |n: f64, epsilon: f64, message: &str| {
+            matches_type(
+                &AssertionType::Number { n, epsilon },
+                &EventRecord::from_message(message),
+            )
+        };
+        assert!(matches(42.0, 0.0, "42"));
+        assert!(matches(42.0, 0.0, "42.0"));
+        assert!(matches(42.0, 0.0, " 4.2e1\n"));
+        assert!(matches(-1.5, 0.0, "-1.5"));
+        assert!(matches(0.25, 0.01, "0.2549"));
+        assert!(matches(0.25, 0.01, "0.2451"));
+        assert!(!matches(0.25, 0.01, "0.2601"));
+        assert!(!matches(42.0, 0.0, "42.5"));
+        assert!(!matches(42.0, 1.0, "forty two"));
+        assert!(!matches(42.0, 1.0, "42 ms"));
+        assert!(!matches(42.0, 1.0, ""));
+        assert!(!matches(f64::NAN, 1.0, "NaN"));
+        assert_eq!(
+            AssertionType::Number {
+                n: 0.25,
+                epsilon: 0.01
+            }
+            .to_string(),
+            "0.25±0.01"
+        );
+
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let number = asserter.matches_number(3.0, 0.5);
+        info!("three");
+        (!&number).assert();
+        info!("{}", 3.25);
+        number.assert();
+        drop(guard);
     }
-}
-impl BitOr for &Assertion {
-    type Output = Assertion;
-    fn bitor(self, rhs: Self) -> Self::Output {
-        Assertion(AssertionWrapper::Or {
-            lhs: Box::new(self.clone()),
-            rhs: Box::new(rhs.clone()),
-        })
+
+    #[test]
+    fn assertion_report() {
+        let asserter = Layer::default();
+        assert_eq!(
+            asserter.assertion_report(),
+            "pattern | type | state | matches\n--------+------+-------+--------"
+        );
+        let _one = asserter.matches("one");
+        let _retrying = asserter.matches_nth("retrying", 3);
+        let _user = asserter.field_display_eq("user", "bob");
+        let _persistent = asserter.matches("two").persist();
+        let _matched = asserter.matches("three");
+        drop(asserter.matches("dropped"));
+        asserter.check_message("retrying");
+        asserter.check_message("two");
+        asserter.check_message("two");
+        asserter.check_message("three");
+        assert_eq!(
+            asserter.assertion_report(),
+            "\
+pattern   | type          | state   | matches
+----------+---------------+---------+--------
+one       | matches       | pending | 0
+retrying  | matches       | pending | 1
+user=%bob | field_display | pending | 0
+two       | matches       | matched | 2"
+        );
     }
-}
-impl BitOr<&Assertion> for Assertion {
-    type Output = Self;
-    fn bitor(self, rhs: &Assertion) -> Self::Output {
-        Assertion(AssertionWrapper::Or {
-            lhs: Box::new(self.clone()),
-            rhs: Box::new(rhs.clone()),
-        })
+
+    #[test]
+    fn same_thread_as() {
+        let asserter = Layer::default();
+        let dispatch = tracing::Dispatch::new(Registry::default().with(asserter.clone()));
+        let enter = asserter.matches("enter");
+        let exit = asserter.matches("exit");
+        let other = asserter.matches("other");
+        let span = asserter.span_field_bool("critical", "held", true);
+        assert_eq!(enter.same_thread_as(&exit), None);
+        tracing::dispatcher::with_default(&dispatch, || {
+            info!("enter");
+            assert_eq!(enter.same_thread_as(&exit), None);
+            let _span = tracing::info_span!("critical", held = true).entered();
+            info!("exit");
+        });
+        std::thread::scope(|scope| {
+            scope.spawn(|| tracing::dispatcher::with_default(&dispatch, || info!("other")));
+        });
+        assert_eq!(enter.same_thread_as(&exit), Some(true));
+        assert_eq!(exit.same_thread_as(&span), Some(true));
+        assert_eq!(enter.same_thread_as(&other), Some(false));
+        assert_eq!((&enter & &other).same_thread_as(&other), Some(true));
+        assert_eq!((&enter | &other).same_thread_as(&exit), Some(true));
+        assert_eq!((!&enter).same_thread_as(&exit), None);
+
+        enter.reset();
+        assert_eq!(enter.same_thread_as(&exit), None);
+        std::thread::scope(|scope| {
+            scope.spawn(|| asserter.check_message("enter"));
+        });
+        assert_eq!(enter.same_thread_as(&other), Some(false));
     }
-}
-impl BitOr<Assertion> for &Assertion {
-    type Output = Assertion;
-    fn bitor(self, rhs: Assertion) -> Self::Output {
-        Assertion(AssertionWrapper::Or {
-            lhs: Box::new(self.clone()),
-            rhs: Box::new(rhs.clone()),
-        })
+
+    #[test]
+    fn snapshot() {
+        let run = || {
+            let asserter = Layer::builder().recording().build();
+            let guard = asserter.set_default();
+            info!(target: "auth", user = "bob", "login");
+            tracing::warn!(target: "db", "slow\nquery");
+            asserter.check_message("unrecorded");
+            tracing::trace!(retries = 3);
+            drop(guard);
+            asserter.snapshot()
+        };
+        let first = run();
+        assert_eq!(
+            first,
+            format!(
+                " INFO auth: login user=bob\n WARN db: slow\\nquery\nTRACE {}: retries=3\n",
+                module_path!()
+            )
+        );
+        assert_eq!(run(), first);
+        assert!(Layer::default().snapshot().is_empty());
     }
-}
 
-impl From<&Assertion> for bool {
-    fn from(value: &Assertion) -> Self {
-        use AssertionWrapper::*;
-        match &value.0 {
-            One {
-                assertion,
-                asserter,
-            } => {
-                if asserter.pass_all.load(SeqCst) {
-                    return true;
-                }
-                assertion.boolean.load(std::sync::atomic::Ordering::SeqCst)
-            }
-            And { lhs, rhs } => bool::from(&**lhs) && bool::from(&**rhs),
-            Or { lhs, rhs } => bool::from(&**lhs) || bool::from(&**rhs),
-            Not { assertion } => !bool::from(&**assertion),
-        }
+    #[test]
+    fn level() {
+        let asserter = Layer::default();
+        asserter.set_color(true);
+        let guard = asserter.set_default();
+        let error = asserter.level(Level::ERROR);
+        let warn = asserter.level(Level::WARN);
+        let failed = asserter.matches("connection failed") & &error;
+        let either = &error | &warn;
+        let not_debug = !asserter.level(Level::DEBUG);
+        asserter.check_message("connection failed");
+        (!&error).assert();
+        tracing::warn!("retrying");
+        warn.assert();
+        either.assert();
+        (!&failed).assert();
+        // With the `creation-location` feature the location follows.
+        assert!((!&error)
+            .ansi()
+            .starts_with("!\u{1b}[31m\"level(ERROR)\"\u{1b}[0m"));
+        tracing::error!("giving up");
+        failed.assert();
+        not_debug.assert();
+        tracing::debug!("details");
+        (!&not_debug).assert();
+        drop(guard);
     }
-}
-impl From<Assertion> for bool {
-    fn from(value: Assertion) -> Self {
-        bool::from(&value)
+
+    #[test]
+    fn contains() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let connected = asserter.contains("connected to");
+        let empty = asserter.contains("");
+        let exact = asserter.contains("exact");
+        assert_eq!(asserter.failing(), ["*connected to*", "**", "*exact*"]);
+        info!("disconnected");
+        (!&connected).assert();
+        empty.assert();
+        info!("[12:00:01] connected to 10.0.0.1");
+        connected.assert();
+        info!("exact");
+        exact.assert();
+        drop(guard);
     }
-}
 
-/// The inner assertion shared between assertions and the assertion layer.
-///
-/// You should probably not use this directly.
-#[derive(Debug)]
-struct InnerAssertion {
-    boolean: AtomicBool,
-    assertion_type: AssertionType,
-}
+    #[test]
+    fn never() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let no_panic = asserter.never("panicked");
+        assert_eq!(asserter.0.assertions.lock().unwrap().len(), 1);
+        info!("started");
+        no_panic.assert();
+        tracing::error!("panicked");
+        (!&no_panic).assert();
+        let repeated = no_panic.repeat();
+        repeated.assert();
+        info!("started");
+        (!&no_panic).assert();
 
-struct EventVisitor<'a>(&'a mut String);
-impl Visit for EventVisitor<'_> {
-    fn record_debug(&mut self, _field: &Field, value: &dyn std::fmt::Debug) {
-        *self.0 = format!("{value:?}");
+        no_panic.reset();
+        no_panic.assert();
+        tracing::error!("panicked");
+        (!&no_panic).assert();
+        (!&repeated).assert();
+        drop(guard);
     }
-}
 
-impl<S: Subscriber> tracing_subscriber::layer::Layer<S> for Layer {
-    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
-        // TODO This is a stupid way to access the message, surely there is a better way to get the message.
-        let mut message = String::new();
-        event.record(&mut EventVisitor(&mut message) as &mut dyn Visit);
-        let mut assertions = self.0.assertions.lock().unwrap();
-        let mut i = 0;
-        while i < assertions.len() {
-            let result = match &assertions[i].assertion_type {
-                AssertionType::Matches(expected) => *expected == message,
-                AssertionType::Regex(regex) => regex.is_match(&message),
-            };
-            assertions[i].boolean.store(result, SeqCst);
-            if result {
-                assertions.remove(i);
-            } else {
-                i += 1;
-            }
+    #[test]
+    fn count() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let requests = asserter.count("request");
+        requests.assert_eq(0).assert_at_most(0);
+        for _ in 0..3 {
+            info!("request");
+            info!("response");
         }
+        asserter.check_message("request");
+        assert_eq!(requests.count(), 4);
+        requests
+            .assert_eq(4)
+            .assert_at_least(4)
+            .assert_at_least(1)
+            .assert_at_most(4)
+            .assert_at_most(10);
+        asserter.disable();
+        requests.assert_eq(1);
+        drop(guard);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use tracing::info;
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(
+        expected = "expected \"request\" to be logged 3 times but it was logged 2 times"
+    )]
+    fn count_eq_fail() {
+        let asserter = Layer::default();
+        let requests = asserter.count("request");
+        asserter.check_message("request");
+        asserter.check_message("request");
+        requests.assert_eq(3);
+    }
 
-    use super::*;
-    use tracing_subscriber::{layer::SubscriberExt, Registry};
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    #[should_panic(
+        expected = "expected \"request\" to be logged at least 1 times but it was logged 0 times"
+    )]
+    fn count_at_least_fail() {
+        let asserter = Layer::default();
+        asserter.count("request").assert_at_least(1);
+    }
 
-    #[cfg(feature = "regex")]
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
     #[test]
-    fn regex_pass() {
+    #[should_panic(
+        expected = "expected \"request\" to be logged at most 1 times but it was logged 2 times"
+    )]
+    fn count_at_most_fail() {
         let asserter = Layer::default();
-        let base_subscriber = Registry::default();
-        let subscriber = base_subscriber.with(asserter.clone());
-        let guard = tracing::subscriber::set_default(subscriber);
-        let condition = asserter.regex("01234.6789").unwrap();
-        info!("0123456789");
-        condition.assert();
-        drop(guard);
+        let requests = asserter.count("request");
+        asserter.check_message("request");
+        asserter.check_message("request");
+        requests.assert_at_most(1);
     }
 
-    #[cfg(feature = "regex")]
-    #[should_panic(expected = "\u{1b}[31m\"01234.789\"\u{1b}[0m")]
     #[test]
-    fn regex_fail() {
+    fn matches_ignore_case() {
+        let matches = |expected: &str, message: &str| {
+            matches_type(
+                &AssertionType::MatchesIgnoreCase(expected.into()),
+                &EventRecord::from_message(message),
+            )
+        };
+        assert!(matches("not found", "Not Found"));
+        assert!(matches("NOT FOUND", "not found"));
+        assert!(matches("not found", "not found"));
+        assert!(!matches("not found", "not  found"));
+        assert!(!matches("not found", "not found!"));
+        assert!(!matches("éclair", "Éclair"));
+
         let asserter = Layer::default();
-        let base_subscriber = Registry::default();
-        let subscriber = base_subscriber.with(asserter.clone());
-        let guard = tracing::subscriber::set_default(subscriber);
-        let condition = asserter.regex("01234.789").unwrap();
-        info!("0123456789");
-        condition.assert();
+        let guard = asserter.set_default();
+        let not_found = asserter.matches_ignore_case("not found");
+        let other = asserter.matches("other");
+        assert_eq!(asserter.failing(), ["not found", "other"]);
+        let both = &not_found & &other;
+        info!("Not Found");
+        not_found.assert();
+        assert_eq!(asserter.failing(), ["other", "(not found && other)"]);
+        (!&both).assert();
+        info!("other");
+        both.assert();
         drop(guard);
     }
 
     #[test]
-    fn debug() {
-        #[allow(dead_code)]
-        #[derive(Debug)]
-        struct MyStruct {
-            x: i32,
-            y: i32,
-        }
+    fn predicate() {
         let asserter = Layer::default();
-        let base_subscriber = Registry::default();
-        let subscriber = base_subscriber.with(asserter.clone());
-        let guard = tracing::subscriber::set_default(subscriber);
-        let value = MyStruct { x: 2, y: 3 };
-        let condition = asserter.debug(&value);
-        info!("{value:?}");
-        condition.assert();
+        asserter.set_color(true);
+        let guard = asserter.set_default();
+        let long = asserter.predicate(|message| message.len() > 5);
+        let copy = long.clone();
+        let both = &long & asserter.matches("two");
+        assert!(long
+            .ansi()
+            .starts_with("\u{1b}[31m\"<predicate>\"\u{1b}[0m"));
+        info!("one");
+        (!&long).assert();
+        info!("longer");
+        long.assert();
+        copy.assert();
+        (!&both).assert();
+        info!("two");
+        both.assert();
+        let repeated = long.repeat();
+        (!&repeated).assert();
+        info!("longest");
+        repeated.assert();
         drop(guard);
     }
 
     #[test]
-    fn pass_all() {
+    fn message_field() {
         let asserter = Layer::default();
-        let base_subscriber = Registry::default();
-        let subscriber = base_subscriber.with(asserter.clone());
-        let guard = tracing::subscriber::set_default(subscriber);
+        let guard = asserter.set_default();
+        let login = asserter.matches("login ok");
+        let bob = asserter.matches("\"bob\"");
+        info!(user = "bob", attempt = 2, "login ok");
+        login.assert();
+        (!&bob).assert();
+        login.reset();
+        info!(message = "login ok", user = "bob");
+        login.assert();
+        (!&bob).assert();
+        info!(user = "bob");
+        bob.assert();
+        drop(guard);
+    }
 
-        info!("stuff");
-        let condition = asserter.matches("missing");
-        asserter.disable();
-        info!("more stuff");
-        condition.assert();
-        asserter.enable();
-        (!condition).assert();
+    #[test]
+    fn message_display() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let path = std::path::Path::new(r"C:\logs\app.log");
+        let opened = asserter.matches(r"opened C:\logs\app.log");
+        let written = asserter.matches(r"C:\logs\app.log");
+        info!("opened {}", path.display());
+        opened.assert();
+        info!(message = %path.display());
+        written.assert();
+        drop(guard);
+    }
 
+    #[test]
+    fn field() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let status = asserter.field("status", "404");
+        let user = asserter.field("user", "bob");
+        let other = asserter.field("status", "500");
+        let response = asserter.matches("response");
+        info!(status = 404, user = "bob", "response");
+        status.assert();
+        user.assert();
+        response.assert();
+        (!&other).assert();
         drop(guard);
     }
 
     #[test]
-    #[should_panic(
-        expected = "((\u{1b}[32m\"one\"\u{1b}[0m && \u{1b}[31m\"two\"\u{1b}[0m) || (\u{1b}[31m\"three\"\u{1b}[0m && !\u{1b}[31m\"four\"\u{1b}[0m))"
-    )]
-    fn panics() {
+    fn target() {
         let asserter = Layer::default();
-        let registry = Registry::default();
-        let subscriber = registry.with(asserter.clone());
-        let guard = tracing::subscriber::set_default(subscriber);
-        let one = asserter.matches("one");
-        let two = asserter.matches("two");
-        let three = asserter.matches("three");
-        let four = asserter.matches("four");
-        let assertion = one & two | three & !four;
-        info!("one");
-        asserter.disable();
-        assertion.assert();
-        assert_eq!(assertion.ansi(),"((\u{1b}[32m\"one\"\u{1b}[0m && \u{1b}[32m\"two\"\u{1b}[0m) || (\u{1b}[32m\"three\"\u{1b}[0m && !\u{1b}[32m\"four\"\u{1b}[0m))");
-        asserter.enable();
-        assertion.assert();
+        asserter.set_color(true);
+        let guard = asserter.set_default();
+        let db = asserter.target("my_crate::db");
+        let query = asserter.matches("query") & asserter.target("my_crate::db");
+        assert!(db
+            .ansi()
+            .starts_with("\u{1b}[31m\"target(my_crate::db)\"\u{1b}[0m"));
+        asserter.check_message("query");
+        (!&db).assert();
+        info!(target: "my_crate::http", "request");
+        (!&db).assert();
+        (!&query).assert();
+        info!(target: "my_crate::db", "connected");
+        db.assert();
+        query.assert();
         drop(guard);
     }
 
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
     #[test]
-    fn and() {
+    #[should_panic(expected = "matched 2 of 4, stuck on \"three\"")]
+    fn sequence_progress() {
         let asserter = Layer::default();
-        let registry = Registry::default();
-        let subscriber = registry.with(asserter.clone());
-        let guard = tracing::subscriber::set_default(subscriber);
-        let one = asserter.matches("one");
-        let two = asserter.matches("two");
-        let a = &one & two.clone();
-        let b = one.clone() & &two;
-        let c = &one & &two;
-        let d = one & two;
+        let guard = asserter.set_default();
+        let sequence = asserter.sequence(["one", "two", "three", "four"]);
+        info!("two");
         info!("one");
+        info!("other");
         info!("two");
-        a.assert();
-        b.assert();
-        c.assert();
-        d.assert();
+        info!("four");
         drop(guard);
+        sequence.assert();
     }
 
     #[test]
-    fn or() {
+    fn set_color() {
         let asserter = Layer::default();
-        let registry = Registry::default();
-        let subscriber = registry.with(asserter.clone());
-        let guard = tracing::subscriber::set_default(subscriber);
+        asserter.set_color(false);
         let one = asserter.matches("one");
         let two = asserter.matches("two");
-        let a = &one | two.clone();
-        let b = one.clone() | &two;
-        let c = &one | &two;
-        let d = one | two;
-        info!("one");
-        a.assert();
-        b.assert();
-        c.assert();
-        d.assert();
-        drop(guard);
+        let both = &one & !&two;
+        let sequence = asserter.sequence(["one", "two"]);
+        asserter.check_message("one");
+        assert!(both.ansi().starts_with("(\"one\" && !\"two\""));
+        assert_eq!(sequence.ansi(), "[\"one\" -> \"two\"]");
+        asserter.set_color(true);
+        assert!(both
+            .ansi()
+            .starts_with("(\u{1b}[32m\"one\"\u{1b}[0m && !\u{1b}[31m\"two\"\u{1b}[0m"));
     }
 
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
     #[test]
-    fn matches() {
+    fn try_assert() {
         let asserter = Layer::default();
-        let base_subscriber = Registry::default();
-        let subscriber = base_subscriber.with(asserter.clone());
-        let guard = tracing::subscriber::set_default(subscriber);
+        asserter.set_color(false);
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let both = &one & &two;
+        asserter.check_message("one");
+        assert!(one.try_assert().is_ok());
+        let error = both.try_assert().unwrap_err();
+        assert_eq!(error.to_string(), both.ansi());
+        let error: Box<dyn std::error::Error> = Box::new(error);
+        assert!(error.to_string().starts_with("(\"one\" && \"two\""));
+        asserter.check_message("two");
+        assert!(both.try_assert().is_ok());
+    }
 
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    fn assert_all() {
+        let asserter = Layer::default();
+        asserter.set_color(false);
+        let one = asserter.matches("one");
         let two = asserter.matches("two");
         let three = asserter.matches("three");
-        let or = &two | &three;
-        let and = &two & &three;
-        let or2 = two.clone() | three.clone();
-        let and2 = two.clone() & three.clone();
+        asserter.check_message("two");
+        let failure = std::panic::catch_unwind(|| {
+            asserter.assert_all(&[&one, &two, &three]);
+        })
+        .unwrap_err();
+        let lines = failure
+            .downcast_ref::<String>()
+            .unwrap()
+            .lines()
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "2 of 3 assertions failed:");
+        assert!(lines[1].starts_with("\"one\""));
+        assert!(lines[2].starts_with("\"three\""));
 
-        // The assertion is false as message matching `two` has not been encountered.
-        (!&two).assert();
+        asserter.soft_mode(true);
+        asserter.assert_all(&[&one, &two, &three]);
+        assert_eq!(asserter.0.failures.lock().unwrap().len(), 2);
+        asserter.check_message("one");
+        asserter.check_message("three");
+        asserter.soft_mode(false);
+        asserter.0.failures.lock().unwrap().clear();
+        asserter.assert_all(&[&one, &two, &three]);
+    }
 
-        info!("one");
+    #[cfg(feature = "glob")]
+    #[test]
+    fn glob() {
+        let asserter = Layer::default();
+        let matches =
+            |pattern: &str, message: &str| Glob::new(pattern.into()).unwrap().is_match(message);
+        assert!(matches("request_*_completed", "request_42_completed"));
+        assert!(matches("request_*_completed", "request__completed"));
+        assert!(!matches("request_*_completed", "request_42_completed!"));
+        assert!(matches("*a*b", "xaxxab"));
+        assert!(!matches("*a*b", "xaxxa"));
+        assert!(matches("*", ""));
+        assert!(matches("h?llo", "héllo"));
+        assert!(!matches("h?llo", "hllo"));
+        assert!(matches("[a-c]x", "bx"));
+        assert!(!matches("[!a-c]x", "bx"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a-]", "-"));
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "x"));
+        assert_eq!(
+            asserter.glob("[abc").unwrap_err().to_string(),
+            "invalid glob \"[abc\": unclosed `[`"
+        );
+        assert!(asserter.glob("abc\\").is_err());
 
-        // Still false.
-        (!&two).assert();
-        (!&or).assert();
-        (!&or2).assert();
+        let completed = asserter.glob("request_*_completed").unwrap();
+        assert!(completed.ansi().contains("\"request_*_completed\""));
+        asserter.check_message("request_42_failed");
+        (!&completed).assert();
+        asserter.check_message("request_42_completed");
+        completed.assert();
+    }
 
-        info!("two");
+    #[test]
+    fn span_entered() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let entered = asserter.span_entered("process_batch");
+        let other = asserter.span_entered("other");
+        let both = asserter.span_entered("process_batch") & asserter.matches("batch done");
+        let span = tracing::info_span!("process_batch");
+        (!&entered).assert();
+        let enter = span.enter();
+        entered.assert();
+        (!&other).assert();
+        (!&both).assert();
+        info!("batch done");
+        both.assert();
+        drop(enter);
+        // Events never match span assertions.
+        let again = entered.repeat();
+        info!("process_batch");
+        (!&again).assert();
+        span.in_scope(|| {});
+        again.assert();
+        asserter.disable();
+        other.assert();
+        drop(guard);
+    }
 
-        // The assertion is true as a message matching `two` has been encountered.
-        two.assert();
-        or.assert();
-        or2.assert();
-        (!&and).assert();
-        (!&and2).assert();
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    fn events_in_span() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let batch = asserter.events_in_span("process_batch");
+        let item = asserter.events_in_span("item");
+        info!("outside");
+        tracing::info_span!("process_batch").in_scope(|| {
+            info!("start");
+            tracing::info_span!("item").in_scope(|| info!("item"));
+            info!("end");
+        });
+        let span = tracing::info_span!("process_batch");
+        info!(parent: &span, "explicit");
+        asserter.check_message("no span");
+        batch.assert_eq(3);
+        item.assert_eq(1);
+        let failure = std::panic::catch_unwind(|| {
+            batch.assert_eq(2);
+        })
+        .unwrap_err();
+        assert_eq!(
+            failure.downcast_ref::<String>().unwrap(),
+            "expected \"in_span(process_batch)\" to be logged 2 times but it was logged 3 times"
+        );
+        drop(guard);
+    }
 
+    #[test]
+    fn all_any() {
+        let asserter = Layer::default();
+        asserter.set_color(false);
+        let guard = asserter.set_default();
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let three = asserter.matches("three");
+        let four = asserter.matches("four");
+        let all = Assertion::all([&one, &two, &three, &four]).unwrap();
+        let any = Assertion::any([&one, &two, &three]).unwrap();
+        let single = Assertion::all([&one]).unwrap();
+        #[cfg(not(feature = "creation-location"))]
+        {
+            assert_eq!(all.ansi(), "(\"one\" && \"two\" && \"three\" && \"four\")");
+            assert_eq!(any.ansi(), "(\"one\" || \"two\" || \"three\")");
+            assert_eq!(
+                (&one & &two | &three).ansi(),
+                "((\"one\" && \"two\") || \"three\")"
+            );
+            // Only trees built by `all` and `any` are shown flat.
+            assert_eq!(
+                (&one & &two & &three).ansi(),
+                "((\"one\" && \"two\") && \"three\")"
+            );
+            assert_eq!(
+                Assertion::all([&(&one & &two), &three]).unwrap().ansi(),
+                "((\"one\" && \"two\") && \"three\")"
+            );
+        }
+        (!&any).assert();
+        info!("two");
+        any.assert();
+        (!&single).assert();
+        info!("one");
         info!("three");
+        single.assert();
+        (!&all).assert();
+        info!("four");
+        all.assert();
+        drop(guard);
+    }
 
-        // Still true.
-        two.assert();
-        and.assert();
-        and2.assert();
-
-        // If an assertion is created after the message, it will be false.
-        // Each assertion can only be fulfilled based on messages after its creation.
-        let two = asserter.matches("two");
-        (!&two).assert();
-        assert!(!bool::from(two));
+    #[test]
+    fn all_empty() {
+        assert!(Assertion::all([]).is_none());
+        assert!(Assertion::any([]).is_none());
+    }
 
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
+    #[test]
+    fn labeled() {
+        let asserter = Layer::default();
+        asserter.set_color(false);
+        let guard = asserter.set_default();
+        let connected = asserter.matches("connected").labeled("db-connect");
+        let ready = asserter.matches("ready").labeled("startup");
+        let both = &connected & &ready;
+        assert!(connected.ansi().starts_with("[db-connect] \"connected\""));
+        let error = connected.try_assert().unwrap_err().to_string();
+        assert!(error.contains("[db-connect] \"connected\""), "{error}");
+        info!("connected");
+        connected.assert();
+        (!&both).assert();
+        assert!(connected.matched_at().is_some());
+        let again = connected.repeat();
+        assert!(again.ansi().starts_with("[db-connect]"));
+        (!&again).assert();
+        connected.reset();
+        (!&connected).assert();
+        info!("connected");
+        info!("ready");
+        connected.assert();
+        again.assert();
+        both.assert();
         drop(guard);
     }
 
     #[test]
-    fn repeat() {
+    fn unregister() {
         let asserter = Layer::default();
-        let base_subscriber = Registry::default();
-        let subscriber = base_subscriber.with(asserter.clone());
-        let guard = tracing::subscriber::set_default(subscriber);
+        let guard = asserter.set_default();
+        let one = asserter.matches("one");
+        let two = asserter.matches("two");
+        let three = asserter.matches("three").persist();
+        let kept = asserter.matches("kept");
+        let both = &two & &three;
+        let count = asserter.0.assertions.lock().unwrap().len();
+        one.unregister();
+        both.unregister();
+        assert_eq!(asserter.0.assertions.lock().unwrap().len(), count - 3);
+        info!("one");
+        info!("two");
+        info!("three");
+        info!("kept");
+        (!&one).assert();
+        (!&both).assert();
+        kept.assert();
+        one.reset();
+        info!("one");
+        (!&one).assert();
+        drop(guard);
+    }
 
+    #[test]
+    fn status() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
         let one = asserter.matches("one");
         let two = asserter.matches("two");
-        let or = &one | &two;
         let and = &one & &two;
+        let or = &one | &two;
+        let xor = &one ^ &two;
+        let implies = one.implies(&two);
         let not = !&one;
+        for assertion in [&one, &and, &or, &xor, &implies, &not] {
+            assert_eq!(assertion.status(), AssertionStatus::Pending);
+        }
 
         info!("one");
+        for assertion in [&one, &or, &not] {
+            assert_eq!(assertion.status(), AssertionStatus::Matched);
+        }
+        for assertion in [&and, &xor, &implies] {
+            assert_eq!(assertion.status(), AssertionStatus::Pending);
+        }
+
         info!("two");
+        for assertion in [&and, &xor, &implies] {
+            assert_eq!(assertion.status(), AssertionStatus::Matched);
+        }
+        drop(guard);
+    }
 
+    #[test]
+    fn matches_static() {
+        let asserter = Layer::default();
+        asserter.set_color(false);
+        let guard = asserter.set_default();
+        let one = asserter.matches_static("one");
+        let two = asserter.matches_static("two");
+        assert!(one.ansi().starts_with("\"one\""));
+        info!("one");
         one.assert();
+        (!&two).assert();
+        info!("two");
         two.assert();
-        or.assert();
-        and.assert();
-        (!&not).assert();
+        drop(guard);
+    }
 
-        let one2 = one.repeat();
-        let two2 = two.repeat();
-        let or2 = or.repeat();
-        let and2 = and.repeat();
-        let not2 = not.repeat();
+    #[test]
+    fn matches_persistent() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let consuming = asserter.matches("one");
+        let persistent = asserter.matches_persistent("one");
+        let registered = || asserter.0.assertions.lock().unwrap().len();
+        assert_eq!(registered(), 2);
+        info!("one");
+        // The consuming assertion stops observing events once matched.
+        assert_eq!(registered(), 1);
+        let (consuming_at, persistent_at) = (consuming.matched_at(), persistent.matched_at());
+        info!("one");
+        consuming.assert();
+        persistent.assert();
+        assert_eq!(consuming.matched_at(), consuming_at);
+        assert!(persistent.matched_at() > persistent_at);
+        persistent.reset();
+        assert_eq!(registered(), 1);
+        assert!(!bool::from(&persistent));
+        info!("one");
+        persistent.assert();
+        drop(guard);
+    }
 
-        (!&one2).assert();
-        (!&two2).assert();
-        (!&or2).assert();
-        (!&and2).assert();
-        (!(!&not2)).assert();
+    #[test]
+    fn implies() {
+        let asserter = Layer::default();
+        asserter.set_color(true);
+        let guard = asserter.set_default();
+        let opened = asserter.matches("opened");
+        let closed = asserter.matches("closed");
+        let balanced = opened.implies(&closed);
+        let repeated = balanced.repeat();
+        balanced.assert();
+        assert_eq!(balanced.matched_at(), None);
+        info!("opened");
+        (!&balanced).assert();
+        (!&repeated).assert();
+        assert!(balanced
+            .ansi()
+            .starts_with("(\u{1b}[32m\"opened\"\u{1b}[0m => \u{1b}[31m\"closed\"\u{1b}[0m"));
+        info!("closed");
+        balanced.assert();
+        repeated.assert();
+        assert!(balanced.matched_at().is_some());
+        balanced.reset();
+        balanced.assert();
+        info!("opened");
+        (!&balanced).assert();
+        drop(guard);
+    }
 
+    #[test]
+    fn recorded_messages() {
+        let asserter = Layer::builder().recording().build();
+        let guard = asserter.set_default();
         info!("one");
-        info!("two");
-
-        one2.assert();
-        two2.assert();
-        or2.assert();
-        and2.assert();
-        (!&not2).assert();
+        tracing::warn!(count = 2, "two");
+        asserter.check_message("unrecorded");
+        assert_eq!(asserter.recorded_messages(), ["one", "two count=2"]);
+        asserter.clear_recorded();
+        assert!(asserter.recorded_messages().is_empty());
+        assert!(asserter.recorded_at_level(Level::INFO).is_empty());
+        assert!(asserter.targets_seen().is_empty());
+        assert_eq!(asserter.snapshot(), "");
+        info!("three");
+        assert_eq!(asserter.recorded_messages(), ["three"]);
+        drop(guard);
 
+        let unrecorded = Layer::default();
+        let guard = unrecorded.set_default();
+        info!("one");
+        assert!(unrecorded.recorded_messages().is_empty());
         drop(guard);
     }
 
+    #[cfg(any(debug_assertions, not(feature = "debug-only")))]
     #[test]
-    fn reset() {
+    fn assert_before() {
         let asserter = Layer::default();
-        let base_subscriber = Registry::default();
-        let subscriber = base_subscriber.with(asserter.clone());
-        let guard = tracing::subscriber::set_default(subscriber);
-
-        let one = asserter.matches("one");
-        let two = asserter.matches("two");
-        let or = &one | &two;
-        let and = &one & &two;
-        let not = !&one;
-
-        not.assert().reset();
+        asserter.set_color(false);
+        let guard = asserter.set_default();
+        let a = asserter.matches("A");
+        let b = asserter.matches("B");
+        let c = asserter.matches("C");
+        info!("other");
+        info!("B");
+        info!("A");
+        let message = |f: &dyn Fn()| {
+            *std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+                .unwrap_err()
+                .downcast::<String>()
+                .unwrap()
+        };
+        assert_eq!(
+            message(&|| {
+                a.assert_before(&b);
+            }),
+            "expected \"A\" (event 3) before \"B\" (event 2)"
+        );
+        let unmatched = message(&|| {
+            b.assert_before(&c);
+        });
+        assert!(unmatched.starts_with("expected \"B\" (event 2) before \"C\""));
+        assert!(unmatched.ends_with(" (not matched)"));
+        b.assert_before(&a);
+        info!("C");
+        (&a | &b).assert_before(&c);
+        asserter.disable();
+        a.assert_before(&b);
+        drop(guard);
+    }
 
-        info!("one");
-        info!("two");
+    #[test]
+    fn matches_times() {
+        let asserter = Layer::default();
+        let guard = asserter.set_default();
+        let three = asserter.matches_times("retrying", 3);
+        let either = &three | asserter.matches("gave up");
+        for _ in 0..2 {
+            info!("retrying");
+        }
+        (!&three).assert();
+        (!&either).assert();
+        info!("retrying");
+        three.assert();
+        either.assert();
+        info!("retrying");
+        three.assert();
+        three.reset();
+        (!&three).assert();
+        for _ in 0..3 {
+            info!("retrying");
+        }
+        three.assert();
 
-        one.assert().reset();
-        two.assert().reset();
-        or.assert().reset();
-        and.assert().reset();
+        // Resetting while counting starts the count again.
+        three.reset();
+        for _ in 0..2 {
+            info!("retrying");
+        }
+        three.reset();
+        info!("retrying");
+        (!&three).assert();
+        for _ in 0..2 {
+            info!("retrying");
+        }
+        three.assert();
 
-        (!&one).assert();
+        // As does resetting the layer.
+        let two = asserter.matches_times("failed", 2);
+        info!("failed");
+        asserter.reset_all();
+        info!("failed");
         (!&two).assert();
-        (!&or).assert();
-        (!&and).assert();
-        (!&not).assert();
+        info!("failed");
+        two.assert();
+        drop(guard);
+    }
+
+    #[test]
+    fn starts_with_ends_with() {
+        let asserter = Layer::default();
+        asserter.set_color(false);
+        let guard = asserter.set_default();
+        let prefix = asserter.starts_with("[req");
+        let suffix = asserter.ends_with("started");
+        let either = asserter.starts_with("done") | asserter.ends_with("failed");
+        assert!(prefix.ansi().starts_with("\"[req*\""));
+        assert!(suffix.ansi().starts_with("\"*started\""));
+        info!("req 42 started");
+        (!&prefix).assert();
+        suffix.assert();
+        info!("[req 42] completed");
+        prefix.assert();
+        (!&either).assert();
+        info!("request failed");
+        either.assert();
+        drop(guard);
+    }
 
+    #[test]
+    fn builder() {
+        let asserter = Layer::builder().recording().color(false).disabled().build();
+        let guard = asserter.set_default();
+        let missing = asserter.matches("missing");
+        missing.assert();
         info!("one");
-        info!("two");
+        assert_eq!(asserter.recorded_messages(), ["one"]);
+        asserter.enable();
+        assert!(missing.ansi().starts_with("\"missing\""));
+        drop(guard);
 
-        one.assert();
-        two.assert();
-        or.assert();
-        and.assert();
-        (!&not).assert();
+        let colored = Layer::builder().color(true).build();
+        assert!(colored.matches("one").ansi().starts_with("\u{1b}[31m"));
+    }
 
+    #[test]
+    fn field_cmp() {
+        let asserter = Layer::default();
+        asserter.set_color(false);
+        let guard = asserter.set_default();
+        let lt = asserter.field_lt("latency_ms", 1000);
+        let le = asserter.field_le("latency_ms", 1200);
+        let gt = asserter.field_gt("latency_ms", 1200);
+        let ge = asserter.field_ge("latency_ms", 1200.0);
+        let eq = asserter.field_eq("ratio", 0.5);
+        let named = asserter.field_lt("other", 1000);
+        assert!(lt.ansi().starts_with("\"latency_ms < 1000\""));
+        assert!(ge.ansi().starts_with("\"latency_ms >= 1200\""));
+        info!(latency_ms = 1200_u64, other = "5", "done");
+        (!&lt).assert();
+        le.assert();
+        (!&gt).assert();
+        ge.assert();
+        (!&named).assert();
+        info!(latency_ms = -3, ratio = 0.5, "done");
+        lt.assert();
+        eq.assert();
         drop(guard);
     }
 }